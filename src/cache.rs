@@ -0,0 +1,333 @@
+use crate::config::{Config, Task};
+use crate::logs;
+use crate::shell::Shell;
+use crate::watcher;
+use globset::{Glob, GlobSetBuilder};
+use notify::RecursiveMode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Remembers the inputs a task last succeeded with, so it can be skipped while they are
+/// unchanged. Only tasks with `inputs` are cached
+pub struct Cache {
+    /// Where the entries are stored, `<output_dir>/cache`
+    dir: PathBuf,
+    /// Run every task, but still record the entries
+    force: bool,
+    /// Keys worked out so far, so a task that many others depend on is hashed only once a run
+    keys: Mutex<HashMap<String, String>>,
+}
+
+/// What a task last succeeded with
+#[derive(Debug, Deserialize, Serialize)]
+struct CacheEntry {
+    task: String,
+    key: String,
+    created_at: String,
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf, force: bool) -> Self {
+        Self {
+            dir,
+            force,
+            keys: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Hash of everything that decides what a task does: its command, shell, working dir, env,
+    /// the contents of its inputs, and the keys of its dependencies. `None` for tasks without
+    /// `inputs`, which always run
+    pub fn key(&self, config: &Config, name: &str) -> anyhow::Result<Option<String>> {
+        let task = config
+            .get_task(name)
+            .ok_or_else(|| anyhow::anyhow!("Task '{}' not found in project configuration", name))?;
+
+        if task.inputs.is_none() {
+            return Ok(None);
+        }
+
+        self.hash_task(config, name, task).map(Some)
+    }
+
+    /// Work the key out again the next time it's asked for, e.g. when a task is restarted after
+    /// its inputs changed
+    pub fn forget(&self, name: &str) {
+        self.keys.lock().unwrap().remove(name);
+    }
+
+    fn hash_task(&self, config: &Config, name: &str, task: &Task) -> anyhow::Result<String> {
+        if let Some(key) = self.keys.lock().unwrap().get(name) {
+            return Ok(key.clone());
+        }
+
+        let mut hasher = Sha256::new();
+
+        hash_field(&mut hasher, "command", task.command.as_bytes());
+        let shell = Shell::for_task(config, task);
+        hash_field(&mut hasher, "shell", format!("{:?}", shell).as_bytes());
+        hash_field(
+            &mut hasher,
+            "working_dir",
+            task.working_dir.as_deref().unwrap_or_default().as_bytes(),
+        );
+
+        let env: BTreeMap<_, _> = task.env.iter().flatten().collect();
+        for (key, value) in env {
+            hash_field(&mut hasher, "env", format!("{}={}", key, value).as_bytes());
+        }
+
+        let base_dir = config.task_dir(task);
+        for path in matching_files(&base_dir, task.inputs.iter().flatten())? {
+            let relative = path.strip_prefix(&base_dir).unwrap_or(&path);
+            hash_field(&mut hasher, "input", relative.to_string_lossy().as_bytes());
+
+            let content = fs::read(&path)
+                .map_err(|e| anyhow::anyhow!("Failed to read input '{}': {}", path.display(), e))?;
+            hash_field(&mut hasher, "content", &content);
+        }
+
+        for dep in task.depends_on.iter().flatten() {
+            let dep_task = config.get_task(dep).ok_or_else(|| {
+                anyhow::anyhow!("Task '{}' references unknown dependency '{}'", name, dep)
+            })?;
+            hash_field(&mut hasher, "dependency", dep.as_bytes());
+            hash_field(
+                &mut hasher,
+                "dependency_key",
+                self.hash_task(config, dep, dep_task)?.as_bytes(),
+            );
+        }
+
+        let key: String = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        self.keys
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), key.clone());
+
+        Ok(key)
+    }
+
+    /// Whether the task last succeeded with the same key, and all of its outputs still exist
+    pub fn is_fresh(&self, config: &Config, name: &str, task: &Task, key: &str) -> bool {
+        if self.force {
+            return false;
+        }
+
+        let Ok(content) = fs::read_to_string(self.entry_path(name)) else {
+            return false;
+        };
+        let Ok(entry) = toml::from_str::<CacheEntry>(&content) else {
+            return false;
+        };
+
+        let base_dir = config.task_dir(task);
+        entry.key == key
+            && task.outputs.iter().flatten().all(|pattern| {
+                matching_files(&base_dir, [pattern]).is_ok_and(|files| !files.is_empty())
+            })
+    }
+
+    /// Record that the task succeeded with this key
+    pub fn store(&self, name: &str, key: &str) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.dir).map_err(|e| {
+            anyhow::anyhow!("Failed to create cache dir '{}': {}", self.dir.display(), e)
+        })?;
+
+        let entry = CacheEntry {
+            task: name.to_string(),
+            key: key.to_string(),
+            created_at: logs::timestamp(),
+        };
+        fs::write(self.entry_path(name), toml::to_string_pretty(&entry)?)?;
+
+        Ok(())
+    }
+
+    fn entry_path(&self, name: &str) -> PathBuf {
+        self.dir
+            .join(logs::log_file_name(name))
+            .with_extension("toml")
+    }
+}
+
+/// Write a value prefixed with its name and length, so neighbouring values can't run together
+fn hash_field(hasher: &mut Sha256, name: &str, value: &[u8]) {
+    hasher.update(name.as_bytes());
+    hasher.update((value.len() as u64).to_le_bytes());
+    hasher.update(value);
+}
+
+/// All files matching any of the globs, which are relative to `base_dir`, in a stable order.
+/// Globs match the same files as the `watch_files` of the watcher
+fn matching_files<I, S>(base_dir: &Path, patterns: I) -> anyhow::Result<BTreeSet<PathBuf>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut builder = GlobSetBuilder::new();
+    let mut roots = BTreeMap::new();
+
+    for pattern in patterns {
+        let pattern = pattern.as_ref();
+        builder.add(
+            Glob::new(pattern).map_err(|e| anyhow::anyhow!("Invalid glob '{}': {}", pattern, e))?,
+        );
+
+        // only walk the directories the glob can match in
+        let (root, mode) = watcher::watch_root(base_dir, pattern);
+        let recursive = roots.entry(root).or_insert(false);
+        *recursive |= mode == RecursiveMode::Recursive;
+    }
+
+    let globs = builder.build()?;
+    let mut files = BTreeSet::new();
+
+    for (root, recursive) in roots {
+        walk(&root, recursive, &mut |path| {
+            if path
+                .strip_prefix(base_dir)
+                .is_ok_and(|relative| globs.is_match(relative))
+            {
+                files.insert(path);
+            }
+        })?;
+    }
+
+    Ok(files)
+}
+
+/// Call `found` with every file in `dir`, and in its subdirectories when `recursive`
+fn walk(dir: &Path, recursive: bool, found: &mut dyn FnMut(PathBuf)) -> anyhow::Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(anyhow::anyhow!("Failed to read '{}': {}", dir.display(), e)),
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+
+        // symlinked directories aren't followed, so a link back up can't loop forever
+        if entry.file_type()?.is_dir() {
+            if recursive {
+                walk(&path, recursive, found)?;
+            }
+        } else if path.is_file() {
+            found(path);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logs::test_util::temp_dir;
+
+    const CONFIG: &str = r#"
+[tasks.install]
+command = "yarn install"
+inputs = ["package.json", "*.lock"]
+outputs = ["node_modules/.yarn-state"]
+
+[tasks.build]
+command = "yarn build"
+depends_on = ["install"]
+inputs = ["src/**/*.ts"]
+    "#;
+
+    #[test]
+    fn test_key_changes_with_inputs_and_deps() {
+        let root = temp_dir("cache-key");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("package.json"), "{}").unwrap();
+        fs::write(root.join("yarn.lock"), "a").unwrap();
+        fs::write(root.join("src/main.ts"), "x").unwrap();
+        fs::create_dir_all(root.join("src/deep/er")).unwrap();
+        fs::write(root.join("src/deep/er/util.ts"), "y").unwrap();
+
+        let mut config = Config::load_from_string(CONFIG).unwrap();
+        config.base_dir = Some(root.clone());
+        // every run starts with a cache of its own
+        let key = |name: &str| {
+            Cache::new(root.join("cache"), false)
+                .key(&config, name)
+                .unwrap()
+                .unwrap()
+        };
+
+        let install = key("install");
+        let build = key("build");
+        assert_eq!(key("install"), install);
+
+        // a dependency's input changes the key of its dependents too
+        fs::write(root.join("yarn.lock"), "b").unwrap();
+        assert_ne!(key("install"), install);
+        assert_ne!(key("build"), build);
+
+        // `**` reaches into nested directories
+        let build = key("build");
+        fs::write(root.join("src/deep/er/util.ts"), "z").unwrap();
+        assert_ne!(key("build"), build);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_key_is_hashed_once_a_run() {
+        let root = temp_dir("cache-memo");
+        fs::write(root.join("package.json"), "{}").unwrap();
+
+        let mut config = Config::load_from_string(CONFIG).unwrap();
+        config.base_dir = Some(root.clone());
+        let cache = Cache::new(root.join("cache"), false);
+
+        let install = cache.key(&config, "install").unwrap().unwrap();
+        fs::write(root.join("package.json"), "{\"private\": true}").unwrap();
+        assert_eq!(cache.key(&config, "install").unwrap().unwrap(), install);
+
+        cache.forget("install");
+        assert_ne!(cache.key(&config, "install").unwrap().unwrap(), install);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_fresh_entry_needs_outputs() {
+        let root = temp_dir("cache-fresh");
+        fs::write(root.join("package.json"), "{}").unwrap();
+
+        let mut config = Config::load_from_string(CONFIG).unwrap();
+        config.base_dir = Some(root.clone());
+        let task = config.get_task("install").unwrap();
+        let cache = Cache::new(root.join("cache"), false);
+
+        let key = cache.key(&config, "install").unwrap().unwrap();
+        assert!(!cache.is_fresh(&config, "install", task, &key));
+
+        cache.store("install", &key).unwrap();
+        assert!(!cache.is_fresh(&config, "install", task, &key));
+
+        fs::create_dir_all(root.join("node_modules")).unwrap();
+        fs::write(root.join("node_modules/.yarn-state"), "").unwrap();
+        assert!(cache.is_fresh(&config, "install", task, &key));
+
+        let forced = Cache::new(root.join("cache"), true);
+        assert!(!forced.is_fresh(&config, "install", task, &key));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,5 +1,5 @@
+use crate::commands::{add, edit, graph, init, list, logs, remove, summary};
 use clap::{Parser, Subcommand};
-use crate::commands::add;
 
 #[derive(Parser, Debug)]
 pub struct Cli {
@@ -9,12 +9,58 @@ pub struct Cli {
 
 #[derive(Debug, Subcommand)]
 pub enum Commands {
+    /// Create a config file in the current directory
+    Init(init::InitArgs),
     /// Add a new task
-    Add(add::AddArgs),
+    Add(Box<add::AddArgs>),
+    /// Remove a task, and the parsers only it used
+    Remove(remove::RemoveArgs),
+    /// Change a task with the add wizard, starting from its current values
+    Edit(edit::EditArgs),
+    /// Run tasks after their dependencies, or pick one to run when no name is given
     Run {
-        /// the task name to run
+        /// the task names to run, dependencies they share run only once
+        names: Vec<String>,
+
+        /// also run every task that depends on them, directly or indirectly, afterwards
+        #[arg(long)]
+        dependents: bool,
+
+        /// keep running tasks that do not depend on a failed task, and report every failure
+        #[arg(long)]
+        keep_going: bool,
+
+        /// run every task, even the ones whose inputs are unchanged
+        #[arg(long)]
+        force: bool,
+
+        /// start every line of output with the time it was printed
+        #[arg(long)]
+        timestamps: bool,
+
+        /// print the output of every task in one piece when it finishes, instead of as it comes
+        #[arg(long)]
+        group: bool,
+
+        /// show a dashboard with the state and output of every task, to restart and stop them
+        #[arg(long)]
+        tui: bool,
+    },
+    /// Run a task and rerun it whenever one of its watch_files changes
+    Watch {
+        /// the task name to watch
         name: String,
+
+        /// milliseconds to wait for a burst of changes to settle before reloading
+        #[arg(long, default_value_t = 300)]
+        debounce: u64,
     },
+    /// List the tasks with their descriptions
+    List(list::ListArgs),
+    /// Draw the dependency graph, of all tasks or of one task
+    Graph(graph::GraphArgs),
+    /// Show the logs of a task from a previous run
+    Logs(logs::LogsArgs),
     /// Print the summary of the configuration to see what it should do
-    Summary,
+    Summary(summary::SummaryArgs),
 }
@@ -1,147 +1,375 @@
+use anyhow::Result;
 use clap::Args;
+use clap::builder::PossibleValuesParser;
 use dialoguer::{Confirm, Input, MultiSelect, Select};
 use std::collections::HashMap;
-use std::fs;
-use anyhow::Result;
-use toml;
+use std::path::Path;
+
+use crate::config::{Config, Parser, Pattern, Task};
+use crate::document::ConfigDocument;
+use crate::matcher::Action;
+
+/// Parsers that can be attached with `--parser` or picked in the wizard
+pub const PREDEFINED_PARSERS: [&str; 6] = [
+    "yarn-install",
+    "nextjs",
+    "webpack-dev",
+    "nx-serve",
+    "typescript",
+    "jest",
+];
+
+/// Every value the wizard asks for can be given as a flag. Without any flags all of them are
+/// asked for, otherwise only a missing name or command is
+#[derive(Args, Clone, Debug, Default, PartialEq)]
+pub struct AddArgs {
+    /// name of the new task
+    pub name: Option<String>,
+
+    /// the command the task runs
+    #[arg(long, short)]
+    pub command: Option<String>,
+
+    #[arg(long)]
+    pub description: Option<String>,
+
+    /// tasks this task depends on, comma-separated or repeated
+    #[arg(long, value_delimiter = ',')]
+    pub depends_on: Vec<String>,
+
+    /// files to watch, comma-separated or repeated
+    #[arg(long, value_delimiter = ',')]
+    pub watch_files: Vec<String>,
+
+    /// restart the task when a watched file changes, instead of waiting for it to finish
+    #[arg(long)]
+    pub auto_restart: bool,
+
+    #[arg(long)]
+    pub working_dir: Option<String>,
+
+    /// port the task is ready on once it accepts connections
+    #[arg(long)]
+    pub port_check: Option<u16>,
+
+    /// environment variable for the command as KEY=VALUE, repeated for several
+    #[arg(long, value_parser = parse_env)]
+    pub env: Vec<(String, String)>,
+
+    /// pre-defined parser for the task's output, repeated for several
+    #[arg(long, value_parser = PossibleValuesParser::new(PREDEFINED_PARSERS))]
+    pub parser: Vec<String>,
+
+    /// name of a custom parser to add, with the pattern given by --regex and --level
+    #[arg(long, requires_all = ["regex", "level"])]
+    pub custom_parser: Option<String>,
+
+    /// regex of the custom parser's pattern
+    #[arg(long, requires = "custom_parser")]
+    pub regex: Option<String>,
+
+    /// level of the custom parser's pattern, e.g. info, warn, error or success
+    #[arg(long, requires = "custom_parser")]
+    pub level: Option<String>,
+
+    /// name of the value the custom parser's pattern extracts
+    #[arg(long, requires = "custom_parser")]
+    pub extract: Option<String>,
+
+    /// what happens when the custom parser's pattern matches: fail, restart, notify, ignore or run:<task>
+    #[arg(long, requires = "custom_parser", value_parser = parse_action)]
+    pub action: Option<String>,
+
+    /// never prompt, fail when the name or command is missing instead
+    #[arg(long, short)]
+    pub yes: bool,
+}
+
+impl AddArgs {
+    /// The values of an existing task, to prefill the wizard with
+    pub fn from_task(name: &str, task: &Task) -> Self {
+        let mut env: Vec<(String, String)> =
+            task.env.clone().unwrap_or_default().into_iter().collect();
+        env.sort();
+
+        AddArgs {
+            name: Some(name.to_string()),
+            command: Some(task.command.clone()),
+            description: task.description.clone(),
+            depends_on: task.depends_on.clone().unwrap_or_default(),
+            watch_files: task.watch_files.clone().unwrap_or_default(),
+            auto_restart: task.auto_restart == Some(true),
+            working_dir: task.working_dir.clone(),
+            port_check: task.port_check,
+            env,
+            parser: task
+                .parsers
+                .iter()
+                .flatten()
+                .filter(|name| PREDEFINED_PARSERS.contains(&name.as_str()))
+                .cloned()
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether no values were given at all, and the whole wizard should run
+    fn is_empty(&self) -> bool {
+        *self
+            == AddArgs {
+                yes: self.yes,
+                ..Default::default()
+            }
+    }
+}
+
+/// Add a task to the config file at `config_path`, which `config` was loaded from
+pub fn add_task(config: &Config, config_path: &Path, args: &AddArgs) -> Result<(), anyhow::Error> {
+    let tasks: Vec<String> = config.tasks.keys().cloned().collect();
+
+    let mut args = args.clone();
+    if args.is_empty() && !args.yes {
+        prompt_all(&mut args, &tasks)?;
+    } else {
+        prompt_required(&mut args)?;
+    }
+
+    let task_name = args.name.clone().unwrap_or_default();
+    if tasks.contains(&task_name) {
+        return Err(anyhow::anyhow!("Task '{}' already exists", task_name));
+    }
+    if let Some(dep) = args.depends_on.iter().find(|dep| !tasks.contains(dep)) {
+        return Err(anyhow::anyhow!(
+            "Task '{}' depends on unknown task '{}'",
+            task_name,
+            dep
+        ));
+    }
+
+    if let Some(name) = &args.custom_parser
+        && config
+            .parsers
+            .as_ref()
+            .is_some_and(|parsers| parsers.contains_key(name))
+    {
+        return Err(anyhow::anyhow!("Parser '{}' already exists", name));
+    }
+
+    let (new_task, configured_parsers) = build_task(&args);
+
+    // only the new task and parsers are added, the rest of the file stays as it was written
+    let mut document = ConfigDocument::load(config_path)?;
+    document.insert_task(&task_name, &new_task)?;
+
+    let mut parser_names: Vec<&String> = configured_parsers.keys().collect();
+    parser_names.sort();
+    for name in parser_names {
+        // a pre-defined parser that is there already may have been tweaked, so it is kept
+        if !document.has_parser(name) {
+            document.insert_parser(name, &configured_parsers[name])?;
+        }
+    }
+
+    document.validate()?;
+    document.save()?;
+
+    println!("Task added successfully!");
+
+    Ok(())
+}
 
-use crate::config::{Config, Parser, Task};
+/// Ask for a missing name or command, or fail with `--yes`
+fn prompt_required(args: &mut AddArgs) -> Result<(), anyhow::Error> {
+    if args.name.is_none() {
+        args.name = Some(prompt_value("Task name", "a task name", args.yes)?);
+    }
+    if args.command.is_none() {
+        args.command = Some(prompt_value("Command", "--command", args.yes)?);
+    }
 
-#[derive(Args, Debug)]
-pub struct AddArgs {}
+    Ok(())
+}
+
+fn prompt_value(prompt: &str, missing: &str, yes: bool) -> Result<String, anyhow::Error> {
+    if yes {
+        return Err(anyhow::anyhow!(
+            "Missing {}, which can't be asked for with --yes",
+            missing
+        ));
+    }
 
-pub fn add_task() -> Result<(), anyhow::Error> {
-    let tasks = get_tasks_from_config()?;
+    Ok(Input::<String>::new().with_prompt(prompt).interact_text()?)
+}
+
+/// The wizard: ask for every value, starting from the values already in `args`
+pub fn prompt_all(args: &mut AddArgs, tasks: &[String]) -> Result<(), anyhow::Error> {
     let task_name = Input::<String>::new()
         .with_prompt("Task name")
+        .with_initial_text(args.name.clone().unwrap_or_default())
         .interact_text()?;
 
     let command = Input::<String>::new()
         .with_prompt("Command")
+        .with_initial_text(args.command.clone().unwrap_or_default())
         .interact_text()?;
 
     let description: String = Input::<String>::new()
         .with_prompt("Description (optional)")
+        .with_initial_text(args.description.clone().unwrap_or_default())
         .allow_empty(true)
         .interact_text()?;
 
+    let depends_on_defaults: Vec<bool> = tasks
+        .iter()
+        .map(|task| args.depends_on.contains(task))
+        .collect();
     let depends_on_selection = MultiSelect::new()
         .with_prompt("Select tasks this task depends on (optional)")
-        .items(&tasks)
+        .items(tasks)
+        .defaults(&depends_on_defaults)
         .interact()?;
 
-    let depends_on: Option<Vec<String>> = if depends_on_selection.is_empty() {
-        None
-    } else {
-        Some(
-            depends_on_selection
-                .into_iter()
-                .map(|i| tasks[i].clone())
-                .collect(),
-        )
-    };
-
     let watch_files_input: String = Input::<String>::new()
         .with_prompt("Watch files (optional, comma-separated)")
+        .with_initial_text(args.watch_files.join(", "))
         .allow_empty(true)
         .interact_text()?;
 
-    let watch_files: Vec<String> = watch_files_input
-        .split(',')
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty())
-        .collect();
-
     let auto_restart = Confirm::new()
         .with_prompt("Auto-restart on file change?")
-        .default(false)
+        .default(args.auto_restart)
         .interact()?;
 
     let working_dir: String = Input::<String>::new()
         .with_prompt("Working directory (optional)")
+        .with_initial_text(args.working_dir.clone().unwrap_or_default())
         .allow_empty(true)
         .interact_text()?;
 
     let port_check_input: String = Input::<String>::new()
         .with_prompt("Port to check (optional)")
+        .with_initial_text(
+            args.port_check
+                .map(|port| port.to_string())
+                .unwrap_or_default(),
+        )
         .allow_empty(true)
         .interact_text()?;
 
-    let port_check = if port_check_input.is_empty() {
-        None
-    } else {
-        port_check_input.parse::<u16>().ok()
-    };
+    let env_initial: Vec<String> = args
+        .env
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+    let env_input: String = Input::<String>::new()
+        .with_prompt("Environment variables (optional, KEY=VALUE, comma-separated)")
+        .with_initial_text(env_initial.join(", "))
+        .allow_empty(true)
+        .validate_with(|input: &String| {
+            split_list(input).try_for_each(|var| parse_env(var).map(|_| ()))
+        })
+        .interact_text()?;
 
-    let configured_parsers = configure_parsers()?;
-
-    let mut config = read_config()?;
-    let new_task = Task {
-        command,
-        description: if description.is_empty() {
-            None
-        } else {
-            Some(description)
-        },
-        depends_on,
-        watch_files: if watch_files.is_empty() {
-            None
-        } else {
-            Some(watch_files)
-        },
-        auto_restart: if auto_restart { Some(true) } else { None },
-        working_dir: if working_dir.is_empty() {
-            None
-        } else {
-            Some(working_dir)
-        },
-        port_check,
-        env: None, // Added missing field
-        parsers: if configured_parsers.is_empty() {
-            None
-        } else {
-            Some(configured_parsers.keys().cloned().collect())
-        },
-    };
+    args.name = Some(task_name);
+    args.command = Some(command);
+    args.description = Some(description).filter(|s| !s.is_empty());
+    args.depends_on = depends_on_selection
+        .into_iter()
+        .map(|i| tasks[i].clone())
+        .collect();
+    args.watch_files = split_list(&watch_files_input).map(str::to_string).collect();
+    args.auto_restart = auto_restart;
+    args.working_dir = Some(working_dir).filter(|s| !s.is_empty());
+    args.port_check = port_check_input.parse::<u16>().ok();
+    args.env = split_list(&env_input)
+        .filter_map(|var| parse_env(var).ok())
+        .collect();
+
+    configure_parsers(args)
+}
 
-    config.tasks.insert(task_name, new_task);
+/// The task and the parsers to add for it
+pub fn build_task(args: &AddArgs) -> (Task, HashMap<String, Parser>) {
+    let mut parsers: HashMap<String, Parser> = args
+        .parser
+        .iter()
+        .map(|name| (name.clone(), get_predefined_parser(name)))
+        .collect();
 
-    // Correctly handle Option<HashMap> for parsers
-    let parsers_map = config.parsers.get_or_insert_with(HashMap::new);
-    for (name, parser) in configured_parsers {
-        parsers_map.insert(name, parser);
+    if let Some(name) = &args.custom_parser {
+        parsers.insert(
+            name.clone(),
+            Parser {
+                patterns: vec![Pattern {
+                    regex: args.regex.clone().unwrap_or_default(),
+                    level: args.level.clone().unwrap_or_default(),
+                    extract: args.extract.clone(),
+                    action: args.action.clone(),
+                }],
+            },
+        );
     }
 
-    write_config(&config)?;
+    let mut parser_names: Vec<String> = parsers.keys().cloned().collect();
+    parser_names.sort();
 
-    println!("Task added successfully!");
+    let task = Task {
+        command: args.command.clone().unwrap_or_default(),
+        description: args.description.clone(),
+        depends_on: Some(args.depends_on.clone()).filter(|deps| !deps.is_empty()),
+        watch_files: Some(args.watch_files.clone()).filter(|files| !files.is_empty()),
+        auto_restart: if args.auto_restart { Some(true) } else { None },
+        working_dir: args.working_dir.clone(),
+        port_check: args.port_check,
+        ready_on: None,
+        ready_timeout: None,
+        port_poll_interval: None,
+        env: Some(args.env.iter().cloned().collect()).filter(|env: &HashMap<_, _>| !env.is_empty()),
+        shell: None,
+        tags: None,
+        inputs: None,
+        outputs: None,
+        timeout: None,
+        kill_grace: None,
+        retries: None,
+        retry_delay: None,
+        retry_backoff: None,
+        retry_on: None,
+        parsers: Some(parser_names).filter(|names| !names.is_empty()),
+    };
 
-    Ok(())
+    (task, parsers)
 }
 
-fn get_tasks_from_config() -> Result<Vec<String>, anyhow::Error> {
-    let config = read_config()?;
-    Ok(config.tasks.keys().cloned().collect())
+fn split_list(input: &str) -> impl Iterator<Item = &str> {
+    input.split(',').map(str::trim).filter(|s| !s.is_empty())
 }
 
-fn read_config() -> Result<Config, anyhow::Error> {
-    let config_str = fs::read_to_string("taskr.toml").unwrap_or_default();
-    let config: Config = toml::from_str(&config_str)?;
-    Ok(config)
+fn parse_env(var: &str) -> Result<(String, String), String> {
+    match var.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("Expected KEY=VALUE, got '{}'", var)),
+    }
 }
 
-fn write_config(config: &Config) -> Result<(), anyhow::Error> {
-    let config_str = toml::to_string_pretty(config)?;
-    fs::write("taskr.toml", config_str)?;
-    Ok(())
+fn parse_action(action: &str) -> Result<String, String> {
+    match Action::parse(action) {
+        Some(_) => Ok(action.to_string()),
+        None => Err(format!("Unknown action '{}'", action)),
+    }
 }
 
-fn configure_parsers() -> Result<HashMap<String, Parser>, anyhow::Error> {
-    let mut parsers = HashMap::new();
-    if Confirm::new()
-        .with_prompt("Do you want to add a parser for this task's output?")
-        .interact()? // Corrected missing block
-    {
+fn configure_parsers(args: &mut AddArgs) -> Result<(), anyhow::Error> {
+    let mut confirm =
+        Confirm::new().with_prompt("Do you want to add a parser for this task's output?");
+    // when editing a task that has parsers, enter goes on to keep them
+    if !args.parser.is_empty() {
+        confirm = confirm.default(true);
+    }
+
+    if confirm.interact()? {
         let parser_options = vec!["pre-defined", "custom"];
         let selection = Select::new()
             .with_prompt("Choose a parser type")
@@ -151,17 +379,17 @@ fn configure_parsers() -> Result<HashMap<String, Parser>, anyhow::Error> {
 
         match selection {
             0 => {
-                let predefined_parsers = vec!["yarn-install", "nextjs", "webpack-dev", "nx-serve", "typescript", "jest"];
+                let defaults = PREDEFINED_PARSERS.map(|name| args.parser.iter().any(|p| p == name));
                 let selections = MultiSelect::new()
                     .with_prompt("Select pre-defined parsers")
-                    .items(&predefined_parsers)
+                    .items(&PREDEFINED_PARSERS)
+                    .defaults(&defaults)
                     .interact()?;
 
-                for selection in selections {
-                    let parser_name = predefined_parsers[selection].to_string();
-                    let parser = get_predefined_parser(&parser_name);
-                    parsers.insert(parser_name, parser);
-                }
+                args.parser = selections
+                    .into_iter()
+                    .map(|selection| PREDEFINED_PARSERS[selection].to_string())
+                    .collect();
             }
             1 => {
                 let name = Input::<String>::new()
@@ -175,23 +403,40 @@ fn configure_parsers() -> Result<HashMap<String, Parser>, anyhow::Error> {
                     .interact_text()?;
                 let extract = Input::<String>::new()
                     .with_prompt("Extract value (optional)")
+                    .allow_empty(true)
+                    .interact_text()?;
+                let action = Input::<String>::new()
+                    .with_prompt(
+                        "Action on match: fail, restart, notify, ignore or run:<task> (optional)",
+                    )
+                    .allow_empty(true)
+                    .validate_with(|input: &String| {
+                        if input.is_empty() {
+                            Ok(())
+                        } else {
+                            parse_action(input).map(|_| ())
+                        }
+                    })
                     .interact_text()?;
 
-                let mut custom_parser = Parser {
-                    patterns: Vec::new(),
+                args.custom_parser = Some(name);
+                args.regex = Some(pattern);
+                args.level = Some(level);
+                args.extract = if extract.is_empty() {
+                    None
+                } else {
+                    Some(extract)
+                };
+                args.action = if action.is_empty() {
+                    None
+                } else {
+                    Some(action)
                 };
-                custom_parser.patterns.push(crate::config::Pattern {
-                    regex: pattern,
-                    level,
-                    extract: if extract.is_empty() { None } else { Some(extract) },
-                    action: None, // Added missing field
-                });
-                parsers.insert(name, custom_parser);
             }
             _ => {}
         }
     }
-    Ok(parsers)
+    Ok(())
 }
 
 fn get_predefined_parser(name: &str) -> Parser {
@@ -322,4 +567,49 @@ fn get_predefined_parser(name: &str) -> Parser {
         _ => {}
     }
     parser
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser as _;
+
+    #[derive(clap::Parser)]
+    struct Cli {
+        #[command(flatten)]
+        add: AddArgs,
+    }
+
+    #[test]
+    fn test_build_task_from_flags() {
+        let cli = Cli::try_parse_from([
+            "add",
+            "web",
+            "--command",
+            "yarn dev",
+            "--depends-on",
+            "install,codegen",
+            "--env",
+            "PORT=3000",
+            "--env",
+            "NODE_ENV=development",
+            "--parser",
+            "nextjs",
+            "--yes",
+        ])
+        .unwrap();
+        assert!(!cli.add.is_empty());
+
+        let (task, parsers) = build_task(&cli.add);
+        assert_eq!(task.command, "yarn dev");
+        assert_eq!(
+            task.depends_on,
+            Some(vec!["install".to_string(), "codegen".to_string()])
+        );
+        assert_eq!(task.env.unwrap()["PORT"], "3000");
+        assert_eq!(task.parsers, Some(vec!["nextjs".to_string()]));
+        assert!(parsers.contains_key("nextjs"));
+
+        assert!(Cli::try_parse_from(["add", "--env", "PORT"]).is_err());
+        assert!(Cli::try_parse_from(["add", "--regex", "x"]).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use anyhow::Result;
+use clap::Args;
+use console::Term;
+use std::path::Path;
+
+use crate::commands::add::{self, AddArgs, PREDEFINED_PARSERS};
+use crate::config::{Config, Task};
+use crate::document::ConfigDocument;
+
+#[derive(Args, Debug)]
+pub struct EditArgs {
+    /// the task to edit
+    pub task: String,
+}
+
+/// Go through the add wizard for an existing task, starting from its current values. Renaming
+/// the task also renames it where other tasks and parsers refer to it
+pub fn edit_task(
+    config: &Config,
+    config_path: &Path,
+    args: &EditArgs,
+) -> Result<(), anyhow::Error> {
+    let task = config.get_task(&args.task).ok_or_else(|| {
+        anyhow::anyhow!("Task '{}' not found in project configuration", args.task)
+    })?;
+
+    if !Term::stderr().is_term() {
+        return Err(anyhow::anyhow!(
+            "Editing a task needs a terminal to prompt in"
+        ));
+    }
+
+    let mut others: Vec<String> = config
+        .tasks
+        .keys()
+        .filter(|name| **name != args.task)
+        .cloned()
+        .collect();
+    others.sort();
+
+    let mut values = AddArgs::from_task(&args.task, task);
+    add::prompt_all(&mut values, &others)?;
+
+    let new_name = values.name.clone().unwrap_or_default();
+    if new_name != args.task && config.has_task(&new_name) {
+        return Err(anyhow::anyhow!("Task '{}' already exists", new_name));
+    }
+    if let Some(name) = &values.custom_parser
+        && config
+            .parsers
+            .as_ref()
+            .is_some_and(|parsers| parsers.contains_key(name))
+    {
+        return Err(anyhow::anyhow!("Parser '{}' already exists", name));
+    }
+
+    let (edited, configured_parsers) = add::build_task(&values);
+    let edited = merge(task, edited);
+
+    let mut document = ConfigDocument::load(config_path)?;
+    document.update_task(&args.task, task, &new_name, &edited)?;
+    if new_name != args.task {
+        document.rename_references(&args.task, &new_name);
+    }
+
+    let mut parser_names: Vec<&String> = configured_parsers.keys().collect();
+    parser_names.sort();
+    for name in parser_names {
+        if !document.has_parser(name) {
+            document.insert_parser(name, &configured_parsers[name])?;
+        }
+    }
+
+    document.validate()?;
+    document.save()?;
+
+    println!("Task '{}' updated successfully!", new_name);
+
+    Ok(())
+}
+
+/// The task with the values the wizard asks for taken from `edited`, and everything else, like
+/// its custom parsers, kept as it was
+fn merge(task: &Task, edited: Task) -> Task {
+    // custom parsers can't be picked in the wizard, so they stay. Parsers keep their order
+    let mut parsers: Vec<String> = task
+        .parsers
+        .iter()
+        .flatten()
+        .filter(|name| {
+            !PREDEFINED_PARSERS.contains(&name.as_str())
+                || edited.parsers.iter().flatten().any(|p| p == *name)
+        })
+        .cloned()
+        .collect();
+    for name in edited.parsers.iter().flatten() {
+        if !parsers.contains(name) {
+            parsers.push(name.clone());
+        }
+    }
+
+    Task {
+        command: edited.command,
+        description: edited.description,
+        depends_on: edited.depends_on,
+        watch_files: edited.watch_files,
+        // an explicit `auto_restart = false` stays
+        auto_restart: edited
+            .auto_restart
+            .or(task.auto_restart.filter(|restart| !restart)),
+        working_dir: edited.working_dir,
+        port_check: edited.port_check,
+        env: edited.env,
+        parsers: Some(parsers).filter(|parsers| !parsers.is_empty()),
+        ..task.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_keeps_values_the_wizard_does_not_ask_for() {
+        let config = Config::load_from_string(
+            r#"
+[tasks.web]
+command = "yarn dev"
+parsers = ["errors", "nextjs"]
+ready_on = "nextjs:success"
+retries = 2
+
+[parsers.errors]
+patterns = [{ regex = "Error", level = "error" }]
+
+[parsers.nextjs]
+patterns = [{ regex = "ready", level = "success" }]
+            "#,
+        )
+        .unwrap();
+        let task = config.get_task("web").unwrap();
+
+        let mut values = AddArgs::from_task("web", task);
+        assert_eq!(values.parser, vec!["nextjs"]);
+
+        values.command = Some("next dev".to_string());
+        values.parser.push("jest".to_string());
+        let (edited, _) = add::build_task(&values);
+
+        let merged = merge(task, edited);
+        assert_eq!(merged.command, "next dev");
+        assert_eq!(
+            merged.parsers,
+            Some(vec![
+                "errors".to_string(),
+                "nextjs".to_string(),
+                "jest".to_string()
+            ])
+        );
+        assert_eq!(merged.ready_on.as_deref(), Some("nextjs:success"));
+        assert_eq!(merged.retries, Some(2));
+    }
+}
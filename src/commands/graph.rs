@@ -0,0 +1,248 @@
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use std::collections::BTreeSet;
+
+use crate::config::Config;
+
+#[derive(Args, Debug)]
+pub struct GraphArgs {
+    /// only show this task and everything it depends on
+    pub task: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = GraphFormat::Tree)]
+    pub format: GraphFormat,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+    Tree,
+}
+
+/// The part of the `depends_on` graph being rendered
+struct Graph<'a> {
+    config: &'a Config,
+    /// Sorted, so the output is the same on every run
+    tasks: BTreeSet<&'a str>,
+}
+
+pub fn show_graph(config: &Config, args: &GraphArgs) -> Result<(), anyhow::Error> {
+    let graph = Graph::new(config, args.task.as_deref())?;
+
+    let output = match args.format {
+        GraphFormat::Dot => graph.to_dot(),
+        GraphFormat::Mermaid => graph.to_mermaid(),
+        GraphFormat::Tree => graph.to_tree(),
+    };
+
+    print!("{}", output);
+
+    Ok(())
+}
+
+impl<'a> Graph<'a> {
+    /// The whole graph, or the subgraph of one task and everything it depends on
+    fn new(config: &'a Config, task: Option<&str>) -> Result<Self, anyhow::Error> {
+        let tasks = match task {
+            Some(task) => {
+                if !config.has_task(task) {
+                    return Err(anyhow::anyhow!(
+                        "Task '{}' not found in project configuration",
+                        task
+                    ));
+                }
+
+                let order = config.get_exec_order(task)?;
+                config
+                    .tasks
+                    .keys()
+                    .filter(|name| order.contains(name))
+                    .map(String::as_str)
+                    .collect()
+            }
+            None => config.tasks.keys().map(String::as_str).collect(),
+        };
+
+        Ok(Self { config, tasks })
+    }
+
+    fn deps(&self, task: &str) -> &'a [String] {
+        self.config
+            .get_task(task)
+            .and_then(|t| t.depends_on.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Root tasks have no dependencies, so they are where a run starts
+    fn is_root(&self, task: &str) -> bool {
+        self.deps(task).is_empty()
+    }
+
+    fn to_dot(&self) -> String {
+        let mut out = String::from("digraph taskr {\n    rankdir=LR;\n");
+
+        for task in &self.tasks {
+            if self.is_root(task) {
+                out.push_str(&format!(
+                    "    \"{}\" [style=filled, fillcolor=palegreen];\n",
+                    escape(task)
+                ));
+            } else {
+                out.push_str(&format!("    \"{}\";\n", escape(task)));
+            }
+        }
+
+        for task in &self.tasks {
+            for dep in self.deps(task) {
+                out.push_str(&format!(
+                    "    \"{}\" -> \"{}\";\n",
+                    escape(dep),
+                    escape(task)
+                ));
+            }
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    fn to_mermaid(&self) -> String {
+        // task names can contain characters mermaid does not allow in ids, e.g. `build:web`
+        let id = |task: &str| {
+            let index = self.tasks.iter().position(|t| *t == task).unwrap_or(0);
+            format!("t{}", index)
+        };
+
+        let mut out = String::from("graph LR\n");
+
+        for task in &self.tasks {
+            let class = if self.is_root(task) { ":::root" } else { "" };
+            out.push_str(&format!(
+                "    {}[\"{}\"]{}\n",
+                id(task),
+                task.replace('"', "#quot;"),
+                class
+            ));
+        }
+
+        for task in &self.tasks {
+            for dep in self.deps(task) {
+                out.push_str(&format!("    {} --> {}\n", id(dep), id(task)));
+            }
+        }
+
+        out.push_str("    classDef root fill:#c8f7c5,stroke:#2e7d32\n");
+        out
+    }
+
+    /// Each task nothing else depends on, with the tree of its dependencies below it
+    fn to_tree(&self) -> String {
+        let mut out = String::new();
+
+        let tops = self.tasks.iter().filter(|task| {
+            !self
+                .tasks
+                .iter()
+                .any(|other| self.deps(other).iter().any(|dep| dep == *task))
+        });
+
+        for task in tops {
+            out.push_str(&self.tree_label(task));
+            out.push('\n');
+            self.push_tree(task, "", &mut out);
+        }
+
+        out
+    }
+
+    fn push_tree(&self, task: &str, indent: &str, out: &mut String) {
+        let deps = self.deps(task);
+
+        for (i, dep) in deps.iter().enumerate() {
+            let last = i == deps.len() - 1;
+            let (branch, next) = if last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+
+            out.push_str(&format!("{}{}{}\n", indent, branch, self.tree_label(dep)));
+            self.push_tree(dep, &format!("{}{}", indent, next), out);
+        }
+    }
+
+    fn tree_label(&self, task: &str) -> String {
+        if self.is_root(task) {
+            format!("{} 🌱", task)
+        } else {
+            task.to_string()
+        }
+    }
+}
+
+fn escape(task: &str) -> String {
+    task.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config::load_from_string(
+            r#"
+[tasks.install]
+command = "yarn install"
+
+[tasks.codegen]
+command = "yarn codegen"
+
+[tasks.build]
+command = "yarn build"
+depends_on = ["install", "codegen"]
+
+[tasks.test]
+command = "yarn test"
+depends_on = ["build"]
+
+[tasks.lint]
+command = "yarn lint"
+depends_on = ["install"]
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_tree_of_whole_graph() {
+        let config = config();
+        let graph = Graph::new(&config, None).unwrap();
+
+        assert_eq!(
+            graph.to_tree(),
+            "lint\n\
+             └── install 🌱\n\
+             test\n\
+             └── build\n    \
+                 ├── install 🌱\n    \
+                 └── codegen 🌱\n"
+        );
+    }
+
+    #[test]
+    fn test_dot_of_subgraph() {
+        let config = config();
+        let graph = Graph::new(&config, Some("lint")).unwrap();
+
+        assert_eq!(
+            graph.to_dot(),
+            "digraph taskr {\n    \
+                 rankdir=LR;\n    \
+                 \"install\" [style=filled, fillcolor=palegreen];\n    \
+                 \"lint\";\n    \
+                 \"install\" -> \"lint\";\n\
+             }\n"
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use anyhow::Result;
+use clap::Args;
+use regex::Regex;
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use crate::commands::add::{self, AddArgs};
+use crate::config::Config;
+use crate::document::ConfigDocument;
+
+/// What a new config file starts with
+const STARTER: &str = r#"# Tasks run by taskr, add them with `taskr add` or by hand:
+#
+# [tasks.build]
+# command = "cargo build"
+# description = "Build the project"
+# depends_on = ["install"]
+
+[tasks]
+"#;
+
+/// Pre-defined parsers attached to scripts whose name or command mentions the tool
+const SCRIPT_PARSERS: [(&str, &str); 3] =
+    [("jest", "jest"), ("tsc", "typescript"), ("next", "nextjs")];
+
+/// Tasks created for a Rust project
+const CARGO_TASKS: [&str; 4] = ["build", "check", "test", "clippy"];
+
+#[derive(Args, Debug)]
+pub struct InitArgs {
+    /// only write the starter config, without importing scripts from package.json, a
+    /// Makefile, Cargo.toml or a justfile
+    #[arg(long)]
+    pub no_import: bool,
+}
+
+/// A task for a script found in one of the project's files
+#[derive(Debug)]
+struct Imported {
+    name: String,
+    command: String,
+    parsers: Vec<String>,
+}
+
+/// Create a `taskr.toml` in the current directory, unless it has a config file already, with a
+/// task for every script the project has
+pub fn init(args: &InitArgs) -> Result<(), anyhow::Error> {
+    let dir = env::current_dir()?;
+
+    if let Some(existing) = Config::find_config_file()
+        && existing.parent() == Some(dir.as_path())
+    {
+        return Err(anyhow::anyhow!(
+            "Config file already exists: {}",
+            existing.display()
+        ));
+    }
+
+    let path = dir.join("taskr.toml");
+    let mut document = ConfigDocument::parse(&path, STARTER)?;
+
+    let imported = if args.no_import {
+        Vec::new()
+    } else {
+        import_scripts(&dir)?
+    };
+
+    for task in &imported {
+        let (new_task, parsers) = add::build_task(&AddArgs {
+            name: Some(task.name.clone()),
+            command: Some(task.command.clone()),
+            parser: task.parsers.clone(),
+            ..Default::default()
+        });
+
+        document.insert_task(&task.name, &new_task)?;
+
+        let mut parser_names: Vec<&String> = parsers.keys().collect();
+        parser_names.sort();
+        for name in parser_names {
+            if !document.has_parser(name) {
+                document.insert_parser(name, &parsers[name])?;
+            }
+        }
+    }
+
+    document.validate()?;
+    document.save()?;
+
+    println!("Created {}", path.display());
+    for task in &imported {
+        println!("  ➕ {:<20} {}", task.name, task.command);
+    }
+    println!("Add a task with `taskr add`");
+
+    Ok(())
+}
+
+/// Tasks for the scripts of package.json, the targets of a Makefile, cargo for a Rust project
+/// and the recipes of a justfile. A name that is taken already is prefixed with the tool
+fn import_scripts(dir: &Path) -> Result<Vec<Imported>, anyhow::Error> {
+    let mut sources: Vec<(&str, Vec<Imported>)> = Vec::new();
+
+    if let Some(content) = read_optional(&dir.join("package.json"))? {
+        sources.push(("npm", package_scripts(&content, package_manager(dir))?));
+    }
+
+    if let Some(content) = read_optional(&dir.join("Makefile"))? {
+        let targets = make_targets(&content)
+            .into_iter()
+            .map(|target| imported(&target, format!("make {}", target)))
+            .collect();
+        sources.push(("make", targets));
+    }
+
+    if dir.join("Cargo.toml").exists() {
+        let tasks = CARGO_TASKS
+            .iter()
+            .map(|task| imported(task, format!("cargo {}", task)))
+            .collect();
+        sources.push(("cargo", tasks));
+    }
+
+    for justfile in ["justfile", "Justfile", ".justfile"] {
+        if let Some(content) = read_optional(&dir.join(justfile))? {
+            let recipes = just_recipes(&content)
+                .into_iter()
+                .map(|recipe| imported(&recipe, format!("just {}", recipe)))
+                .collect();
+            sources.push(("just", recipes));
+            break;
+        }
+    }
+
+    let mut names = HashSet::new();
+    let mut tasks = Vec::new();
+
+    for (tool, imported) in sources {
+        for mut task in imported {
+            if !names.insert(task.name.clone()) {
+                task.name = format!("{}-{}", tool, task.name);
+                names.insert(task.name.clone());
+            }
+            tasks.push(task);
+        }
+    }
+
+    Ok(tasks)
+}
+
+fn read_optional(path: &Path) -> Result<Option<String>, anyhow::Error> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(anyhow::anyhow!(
+            "Failed to read '{}': {}",
+            path.display(),
+            e
+        )),
+    }
+}
+
+fn imported(name: &str, command: String) -> Imported {
+    Imported {
+        name: name.to_string(),
+        command,
+        parsers: Vec::new(),
+    }
+}
+
+/// The package manager the lockfile belongs to, npm when there is none
+fn package_manager(dir: &Path) -> &'static str {
+    if dir.join("yarn.lock").exists() {
+        "yarn"
+    } else if dir.join("pnpm-lock.yaml").exists() {
+        "pnpm"
+    } else {
+        "npm"
+    }
+}
+
+/// A task per script in package.json, run with the package manager, with the pre-defined
+/// parsers for the tools the script uses
+fn package_scripts(content: &str, manager: &str) -> Result<Vec<Imported>, anyhow::Error> {
+    #[derive(serde::Deserialize)]
+    struct Package {
+        #[serde(default)]
+        scripts: BTreeMap<String, String>,
+    }
+
+    let package: Package = serde_json::from_str(content)
+        .map_err(|e| anyhow::anyhow!("Failed to parse package.json: {}", e))?;
+
+    Ok(package
+        .scripts
+        .into_iter()
+        .map(|(name, script)| {
+            let parsers = SCRIPT_PARSERS
+                .iter()
+                .filter(|(tool, _)| mentions(&name, tool) || mentions(&script, tool))
+                .map(|(_, parser)| parser.to_string())
+                .collect();
+
+            let command = match manager {
+                "yarn" => format!("yarn {}", name),
+                _ => format!("{} run {}", manager, name),
+            };
+
+            Imported {
+                name,
+                command,
+                parsers,
+            }
+        })
+        .collect())
+}
+
+/// Whether the tool appears as a word of its own, so `next` matches `next build` but not
+/// `nextgen`
+fn mentions(text: &str, tool: &str) -> bool {
+    Regex::new(&format!(r"\b{}\b", regex::escape(tool)))
+        .map(|regex| regex.is_match(text))
+        .unwrap_or(false)
+}
+
+/// The targets of a Makefile that can be run by name, without special targets like `.PHONY`,
+/// pattern rules and variable assignments
+fn make_targets(content: &str) -> Vec<String> {
+    let target = Regex::new(r"^([A-Za-z0-9_][A-Za-z0-9_./-]*)\s*:([^=]|$)").unwrap();
+    let mut targets: Vec<String> = Vec::new();
+
+    for line in content.lines() {
+        if let Some(captures) = target.captures(line) {
+            let name = captures[1].to_string();
+            if !targets.contains(&name) {
+                targets.push(name);
+            }
+        }
+    }
+
+    targets
+}
+
+/// The recipes of a justfile that can be run without arguments
+fn just_recipes(content: &str) -> Vec<String> {
+    let recipe = Regex::new(r"^@?([A-Za-z_][A-Za-z0-9_-]*)([^:]*):([^=]|$)").unwrap();
+    let keywords = ["alias", "export", "import", "mod", "set"];
+
+    content
+        .lines()
+        .filter_map(|line| recipe.captures(line))
+        .filter(|captures| !keywords.contains(&&captures[1]))
+        .filter(|captures| {
+            // parameters need a default, or be variadic with `*`, to run the recipe by name
+            captures[2].split_whitespace().all(|param| {
+                param.contains('=') || param.starts_with('*') || param.starts_with('$')
+            })
+        })
+        .map(|captures| captures[1].to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_starter_is_a_valid_config() {
+        let config = Config::load_from_string(STARTER).unwrap();
+        assert!(config.tasks.is_empty());
+    }
+
+    #[test]
+    fn test_package_scripts_use_manager_and_parsers() {
+        let scripts = package_scripts(
+            r#"{
+                "name": "web",
+                "scripts": {
+                    "dev": "next dev",
+                    "test": "jest --coverage",
+                    "typecheck": "tsc --noEmit",
+                    "codegen": "nextgen"
+                }
+            }"#,
+            "pnpm",
+        )
+        .unwrap();
+
+        let script = |name: &str| scripts.iter().find(|s| s.name == name).unwrap();
+        assert_eq!(script("dev").command, "pnpm run dev");
+        assert_eq!(script("dev").parsers, vec!["nextjs"]);
+        assert_eq!(script("test").parsers, vec!["jest"]);
+        assert_eq!(script("typecheck").parsers, vec!["typescript"]);
+        assert!(script("codegen").parsers.is_empty());
+    }
+
+    #[test]
+    fn test_make_targets_and_just_recipes() {
+        let makefile = "CC := gcc\n.PHONY: build test\nbuild: main.o\n\t$(CC) -o app main.o\n%.o: %.c\n\t$(CC) -c $<\ntest:\n\t./app --test\n";
+        assert_eq!(make_targets(makefile), vec!["build", "test"]);
+
+        let justfile = "set shell := [\"bash\", \"-c\"]\nversion := \"1.0\"\n\n# build it\nbuild:\n    cargo build\n\n@serve port='8080':\n    ./serve {{port}}\n\ndeploy env:\n    ./deploy {{env}}\n";
+        assert_eq!(just_recipes(justfile), vec!["build", "serve"]);
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::Result;
+use clap::Args;
+use console::{Style, Term};
+use dialoguer::FuzzySelect;
+
+use crate::config::{Config, Task};
+
+#[derive(Args, Debug)]
+pub struct ListArgs {
+    /// only list tasks whose name starts with this prefix
+    pub prefix: Option<String>,
+
+    /// only list tasks with this tag
+    #[arg(long)]
+    pub tag: Option<String>,
+}
+
+pub fn list_tasks(config: &Config, args: &ListArgs) -> Result<(), anyhow::Error> {
+    let tasks = filter_tasks(config, args.prefix.as_deref(), args.tag.as_deref());
+
+    if tasks.is_empty() {
+        println!("No tasks found");
+        return Ok(());
+    }
+
+    let width = tasks.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let dim = Style::new().dim();
+
+    for (name, task) in tasks {
+        let mut line = format!(
+            "  {:<width$}  {}",
+            name,
+            task.description.as_deref().unwrap_or(""),
+            width = width
+        );
+
+        if let Some(tags) = task.tags.as_ref().filter(|tags| !tags.is_empty()) {
+            line.push_str(&format!(
+                " {}",
+                dim.apply_to(format!("[{}]", tags.join(", ")))
+            ));
+        }
+
+        println!("{}", line.trim_end());
+    }
+
+    Ok(())
+}
+
+/// Let the user pick a task with a fuzzy search over the task names and descriptions
+pub fn pick_task(config: &Config) -> Result<String, anyhow::Error> {
+    if !Term::stderr().is_term() {
+        return Err(anyhow::anyhow!(
+            "No task name given, and not running in a terminal to pick one"
+        ));
+    }
+
+    let tasks = filter_tasks(config, None, None);
+    if tasks.is_empty() {
+        return Err(anyhow::anyhow!("No tasks in project configuration"));
+    }
+
+    let items: Vec<String> = tasks
+        .iter()
+        .map(|(name, task)| match &task.description {
+            Some(desc) => format!("{} - {}", name, desc),
+            None => name.to_string(),
+        })
+        .collect();
+
+    let selection = FuzzySelect::new()
+        .with_prompt("Select a task to run")
+        .items(&items)
+        .default(0)
+        .interact()?;
+
+    Ok(tasks[selection].0.to_string())
+}
+
+/// Tasks sorted by name, limited to those matching the prefix and tag when given
+fn filter_tasks<'a>(
+    config: &'a Config,
+    prefix: Option<&str>,
+    tag: Option<&str>,
+) -> Vec<(&'a str, &'a Task)> {
+    let mut tasks: Vec<(&str, &Task)> = config
+        .tasks
+        .iter()
+        .filter(|(name, _)| prefix.is_none_or(|prefix| name.starts_with(prefix)))
+        .filter(|(_, task)| tag.is_none_or(|tag| task.tags.iter().flatten().any(|t| t == tag)))
+        .map(|(name, task)| (name.as_str(), task))
+        .collect();
+    tasks.sort_by_key(|(name, _)| *name);
+
+    tasks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_tasks_by_prefix_and_tag() {
+        let config = Config::load_from_string(
+            r#"
+[tasks."build:web"]
+command = "yarn build"
+tags = ["frontend"]
+
+[tasks."build:api"]
+command = "cargo build"
+tags = ["backend"]
+
+[tasks.lint]
+command = "yarn lint"
+tags = ["frontend", "ci"]
+            "#,
+        )
+        .unwrap();
+
+        let names = |tasks: Vec<(&str, &Task)>| -> Vec<String> {
+            tasks
+                .into_iter()
+                .map(|(name, _)| name.to_string())
+                .collect()
+        };
+
+        assert_eq!(
+            names(filter_tasks(&config, Some("build"), None)),
+            vec!["build:api", "build:web"]
+        );
+        assert_eq!(
+            names(filter_tasks(&config, None, Some("frontend"))),
+            vec!["build:web", "lint"]
+        );
+        assert_eq!(
+            names(filter_tasks(&config, Some("build"), Some("frontend"))),
+            vec!["build:web"]
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::Result;
+use clap::Args;
+use std::fs;
+use std::path::Path;
+
+use crate::logs::{self, RunManifest, TaskRecord};
+
+#[derive(Args, Debug)]
+pub struct LogsArgs {
+    /// the task to show the logs of
+    pub task: String,
+
+    /// the run to show, defaults to the latest run that ran the task
+    #[arg(long)]
+    pub run: Option<u32>,
+}
+
+pub fn show_logs(output_dir: &Path, args: &LogsArgs) -> Result<(), anyhow::Error> {
+    let (manifest, record) = match args.run {
+        Some(run) => {
+            let manifest = logs::read_manifest(output_dir, run)?;
+            let record = find_record(&manifest, &args.task)
+                .ok_or_else(|| anyhow::anyhow!("Run {} did not run task '{}'", run, args.task))?;
+            (manifest, record)
+        }
+        None => latest_run_of(output_dir, &args.task)?,
+    };
+
+    if record.skipped {
+        println!(
+            "⏭️  Run {} ({}) • task '{}' was up to date and skipped, so it has no logs",
+            manifest.run, manifest.started_at, record.name
+        );
+        return Ok(());
+    }
+
+    let outcome = match record.exit_code {
+        _ if record.timed_out => "timed out".to_string(),
+        Some(code) => format!("exit code {}", code),
+        None => "exit code none".to_string(),
+    };
+
+    let attempt = if record.attempt > 1 {
+        format!(" • attempt {}", record.attempt)
+    } else {
+        String::new()
+    };
+
+    println!(
+        "📜 Run {} ({}) • task '{}' • {}{} • {:.1}s",
+        manifest.run, manifest.started_at, record.name, outcome, attempt, record.duration_secs
+    );
+    println!("   ─────────────────────────────────");
+
+    let path = output_dir
+        .join(manifest.run.to_string())
+        .join(&record.log_file);
+    let content = fs::read_to_string(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read log file '{}': {}", path.display(), e))?;
+
+    print!("{}", content);
+
+    Ok(())
+}
+
+fn latest_run_of(
+    output_dir: &Path,
+    task: &str,
+) -> Result<(RunManifest, TaskRecord), anyhow::Error> {
+    for run in logs::list_runs(output_dir)?.into_iter().rev() {
+        let Ok(manifest) = logs::read_manifest(output_dir, run) else {
+            continue;
+        };
+
+        // a run that skipped the task has no output of it to show
+        if let Some(record) = find_record(&manifest, task).filter(|record| !record.skipped) {
+            return Ok((manifest, record));
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "No logs found for task '{}' in {}",
+        task,
+        output_dir.display()
+    ))
+}
+
+/// The record of the task's last attempt in the run
+fn find_record(manifest: &RunManifest, task: &str) -> Option<TaskRecord> {
+    manifest
+        .tasks
+        .iter()
+        .rev()
+        .find(|t| t.name == task)
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logs::RunLog;
+    use crate::logs::test_util::{record, temp_dir};
+
+    #[test]
+    fn test_find_record_returns_last_attempt() {
+        let manifest = RunManifest {
+            run: 1,
+            started_at: logs::timestamp(),
+            targets: vec!["test".to_string()],
+            order: vec!["install".to_string(), "test".to_string()],
+            tasks: vec![
+                record("test", 1, Some(1)),
+                record("install", 1, Some(0)),
+                record("test", 2, Some(0)),
+            ],
+        };
+
+        let found = find_record(&manifest, "test").unwrap();
+        assert_eq!((found.attempt, found.exit_code), (2, Some(0)));
+        assert!(find_record(&manifest, "lint").is_none());
+    }
+
+    #[test]
+    fn test_latest_run_passes_over_skipped_task() {
+        let dir = temp_dir("logs-skipped");
+        let order = vec!["build".to_string()];
+
+        let ran = RunLog::create(&dir, &order, &order).unwrap();
+        ran.record(record("build", 1, Some(0))).unwrap();
+
+        let cached = RunLog::create(&dir, &order, &order).unwrap();
+        cached
+            .record(TaskRecord {
+                skipped: true,
+                ..record("build", 1, None)
+            })
+            .unwrap();
+
+        assert!(logs::read_manifest(&dir, 2).unwrap().tasks[0].skipped);
+
+        let (manifest, found) = latest_run_of(&dir, "build").unwrap();
+        assert_eq!(manifest.run, 1);
+        assert!(!found.skipped);
+    }
+}
@@ -1 +1,8 @@
-pub mod add;
\ No newline at end of file
+pub mod add;
+pub mod edit;
+pub mod graph;
+pub mod init;
+pub mod list;
+pub mod logs;
+pub mod remove;
+pub mod summary;
@@ -0,0 +1,149 @@
+use anyhow::Result;
+use clap::Args;
+use console::Term;
+use dialoguer::Confirm;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::config::{Config, Task};
+use crate::document::ConfigDocument;
+
+#[derive(Args, Debug)]
+pub struct RemoveArgs {
+    /// the task to remove
+    pub task: String,
+
+    /// also remove every task that depends on it, directly or indirectly
+    #[arg(long)]
+    pub cascade: bool,
+
+    /// never prompt, refuse to remove a task that others depend on unless --cascade is given
+    #[arg(long, short)]
+    pub yes: bool,
+}
+
+/// Remove a task from the config file at `config_path`, together with the parsers that only
+/// it used
+pub fn remove_task(
+    config: &Config,
+    config_path: &Path,
+    args: &RemoveArgs,
+) -> Result<(), anyhow::Error> {
+    if !config.has_task(&args.task) {
+        return Err(anyhow::anyhow!(
+            "Task '{}' not found in project configuration",
+            args.task
+        ));
+    }
+
+    let mut removed = vec![args.task.as_str()];
+
+    let dependents = config.get_all_dependents(&args.task);
+    if !dependents.is_empty() {
+        let names: Vec<&str> = dependents.iter().map(|name| name.as_str()).collect();
+        if !args.cascade && !confirm_cascade(&args.task, &names, args.yes)? {
+            return Err(anyhow::anyhow!(
+                "Task '{}' is needed by: {}. Use --cascade to remove them too",
+                args.task,
+                names.join(", ")
+            ));
+        }
+        removed.extend(names);
+    }
+
+    let mut document = ConfigDocument::load(config_path)?;
+    for name in &removed {
+        document.remove_task(name)?;
+    }
+
+    let unused = unused_parsers(config, &removed);
+    for parser in &unused {
+        document.remove_parser(parser);
+    }
+
+    document.validate()?;
+    document.save()?;
+
+    for name in &removed {
+        println!("Removed task '{}'", name);
+    }
+    for parser in &unused {
+        println!("Removed parser '{}', no task uses it anymore", parser);
+    }
+
+    Ok(())
+}
+
+fn confirm_cascade(task: &str, dependents: &[&str], yes: bool) -> Result<bool, anyhow::Error> {
+    if yes || !Term::stderr().is_term() {
+        return Ok(false);
+    }
+
+    Ok(Confirm::new()
+        .with_prompt(format!(
+            "Task '{}' is needed by: {}. Remove them too?",
+            task,
+            dependents.join(", ")
+        ))
+        .default(false)
+        .interact()?)
+}
+
+/// Parsers used by the removed tasks that none of the remaining tasks use
+fn unused_parsers<'a>(config: &'a Config, removed: &[&str]) -> BTreeSet<&'a String> {
+    let (gone, kept): (Vec<_>, Vec<_>) = config
+        .tasks
+        .iter()
+        .partition(|(name, _)| removed.contains(&name.as_str()));
+
+    let parsers = |tasks: Vec<(&'a String, &'a Task)>| -> BTreeSet<&'a String> {
+        tasks
+            .into_iter()
+            .flat_map(|(_, task)| task.parsers.iter().flatten())
+            .collect()
+    };
+
+    parsers(gone).difference(&parsers(kept)).copied().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unused_parsers() {
+        let config = Config::load_from_string(
+            r#"
+[tasks.install]
+command = "yarn install"
+parsers = ["yarn-install"]
+
+[tasks.test]
+command = "yarn jest"
+parsers = ["jest", "typescript"]
+
+[tasks.lint]
+command = "yarn tsc"
+parsers = ["typescript"]
+
+[parsers.yarn-install]
+patterns = []
+
+[parsers.jest]
+patterns = []
+
+[parsers.typescript]
+patterns = []
+            "#,
+        )
+        .unwrap();
+
+        let unused: Vec<&String> = unused_parsers(&config, &["test"]).into_iter().collect();
+        assert_eq!(unused, vec!["jest"]);
+
+        let unused: Vec<&String> = unused_parsers(&config, &["test", "lint"])
+            .into_iter()
+            .collect();
+        assert_eq!(unused, vec!["jest", "typescript"]);
+    }
+}
@@ -0,0 +1,238 @@
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::config::{Config, GlobalSettings, Parser, Task};
+
+#[derive(Args, Debug)]
+pub struct SummaryArgs {
+    /// print the fully resolved config in a machine-readable format instead
+    #[arg(long, value_enum, default_value_t = SummaryFormat::Text)]
+    pub format: SummaryFormat,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum SummaryFormat {
+    Text,
+    Json,
+    Toml,
+    Yaml,
+}
+
+/// The config with every default applied and the task graph worked out, for editor plugins and
+/// dashboards
+#[derive(Debug, Serialize)]
+pub struct ResolvedConfig<'a> {
+    pub global: GlobalSettings,
+    pub root_tasks: Vec<&'a String>,
+    pub tasks: BTreeMap<&'a str, ResolvedTask<'a>>,
+    pub parsers: BTreeMap<&'a String, &'a Parser>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResolvedTask<'a> {
+    /// The task with the shell, timeout and kill_grace it runs with filled in
+    #[serde(flatten)]
+    pub task: Task,
+    /// The task's dependencies and the task itself, in the order they run
+    pub exec_order: Vec<String>,
+    /// Tasks that depend on this task directly
+    pub dependents: Vec<&'a String>,
+}
+
+pub fn show_summary(config: &Config, args: &SummaryArgs) -> Result<(), anyhow::Error> {
+    let output = match args.format {
+        SummaryFormat::Text => {
+            print_text(config);
+            return Ok(());
+        }
+        SummaryFormat::Json => serde_json::to_string_pretty(&resolve(config)?)?,
+        SummaryFormat::Toml => toml::to_string_pretty(&resolve(config)?)?,
+        SummaryFormat::Yaml => serde_yaml::to_string(&resolve(config)?)?,
+    };
+
+    println!("{}", output.trim_end());
+
+    Ok(())
+}
+
+pub fn resolve(config: &Config) -> Result<ResolvedConfig<'_>, anyhow::Error> {
+    let mut root_tasks = config.get_root_tasks();
+    root_tasks.sort();
+
+    let mut tasks = BTreeMap::new();
+    for (name, task) in &config.tasks {
+        let mut dependents = config.get_dependent_tasks(name);
+        dependents.sort();
+
+        let settings = config.get_task_settings(task);
+        let task = Task {
+            shell: Some(settings.shell),
+            timeout: settings.timeout,
+            kill_grace: Some(settings.kill_grace),
+            ..task.clone()
+        };
+
+        tasks.insert(
+            name.as_str(),
+            ResolvedTask {
+                task,
+                exec_order: config.get_exec_order(name)?,
+                dependents,
+            },
+        );
+    }
+
+    Ok(ResolvedConfig {
+        global: config.get_global_settings(),
+        root_tasks,
+        tasks,
+        parsers: config.parsers.iter().flatten().collect(),
+    })
+}
+
+fn print_text(config: &Config) {
+    let global = config.get_global_settings();
+
+    println!("\n┌──────────────────────────────────────┐");
+    println!("│        🛠️  Task Runner Config         │");
+    println!("└──────────────────────────────────────┘");
+    println!("  • Log Level     : {}", global.log_level);
+    println!("  • Max Parallel  : {}", global.max_parallel);
+    println!("  • Shell         : {}", global.shell);
+    println!("  • Output Dir    : {}", global.output_dir);
+    println!("  • Kill Grace    : {}s", global.kill_grace);
+    if let Some(timeout) = global.timeout {
+        println!("  • Timeout       : {}s", timeout);
+    }
+
+    println!("\n┌──────────────────────────────────────┐");
+    println!(
+        "│          📋 Tasks ({})                 │",
+        config.tasks.len()
+    );
+    println!("└──────────────────────────────────────┘");
+
+    for (name, task) in &config.tasks {
+        println!("  • {}", name);
+        println!("     ├─ Command       : {}", task.command);
+
+        let settings = config.get_task_settings(task);
+        println!("     ├─ Shell         : {}", settings.shell);
+
+        if let Some(desc) = &task.description {
+            println!("     ├─ Description   : {}", desc);
+        }
+
+        if let Some(tags) = &task.tags {
+            println!("     ├─ Tags          : {}", tags.join(", "));
+        }
+
+        if let Some(deps) = &task.depends_on {
+            println!("     ├─ Dependencies  : {}", deps.join(", "));
+        }
+
+        if let Some(parsers) = &task.parsers {
+            println!("     ├─ Parsers       : {}", parsers.join(", "));
+        }
+
+        if let Some(watch) = &task.watch_files {
+            println!("     ├─ Watching      : {}", watch.join(", "));
+        }
+
+        if let Some(timeout) = settings.timeout {
+            println!("     ├─ Timeout       : {}s", timeout);
+        }
+
+        if task.auto_restart == Some(true) {
+            println!("     ├─ Auto-restart  : ✅ enabled");
+        }
+
+        if let Some(port) = task.port_check {
+            println!("     └─ Port check    : {}", port);
+        } else {
+            println!("     └─ Port check    : (none)");
+        }
+
+        println!();
+    }
+
+    if let Some(parsers) = &config.parsers {
+        println!("┌──────────────────────────────────────┐");
+        println!("│          🔍 Parsers ({})               │", parsers.len());
+        println!("└──────────────────────────────────────┘");
+
+        for (name, parser) in parsers {
+            println!("  • {}", name);
+            println!("     ├─ Pattern count : {}", parser.patterns.len());
+            for pattern in &parser.patterns {
+                println!(
+                    "     └─ Pattern       : {}  [{}]",
+                    pattern.regex, pattern.level
+                );
+            }
+            println!();
+        }
+    }
+
+    let root_tasks = config.get_root_tasks();
+    if !root_tasks.is_empty() {
+        println!("┌──────────────────────────────────────┐");
+        println!("│       🌱 Root Tasks (no deps)         │");
+        println!("└──────────────────────────────────────┘");
+        for task in root_tasks {
+            println!("  • {}", task);
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DEFAULT_SHELL;
+
+    #[test]
+    fn test_resolve_applies_defaults_and_graph() {
+        let config = Config::load_from_string(
+            r#"
+[global]
+max_parallel = 2
+
+[tasks.install]
+command = "yarn install"
+
+[tasks.build]
+command = "yarn build"
+depends_on = ["install"]
+
+[tasks.test]
+command = "yarn test"
+depends_on = ["build"]
+            "#,
+        )
+        .unwrap();
+
+        let resolved = resolve(&config).unwrap();
+
+        assert_eq!(resolved.global.max_parallel, 2);
+        assert_eq!(resolved.global.shell, DEFAULT_SHELL);
+        assert_eq!(resolved.global.output_dir, ".task-logs");
+        assert_eq!(
+            resolved.tasks["build"].task.shell.as_deref(),
+            Some(DEFAULT_SHELL)
+        );
+        assert_eq!(resolved.tasks["build"].task.kill_grace, Some(5));
+        assert_eq!(resolved.root_tasks, vec!["install"]);
+        assert_eq!(
+            resolved.tasks["test"].exec_order,
+            vec!["install", "build", "test"]
+        );
+        assert_eq!(resolved.tasks["install"].dependents, vec!["build"]);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&resolved).unwrap()).unwrap();
+        assert_eq!(json["tasks"]["build"]["command"], "yarn build");
+    }
+}
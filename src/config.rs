@@ -7,11 +7,24 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 
+use crate::matcher::{Action, MatchSelector};
+
+/// Settings used when neither the task nor `[global]` sets them
+const DEFAULT_LOG_LEVEL: &str = "info";
+const DEFAULT_MAX_PARALLEL: u32 = 4;
+const DEFAULT_OUTPUT_DIR: &str = ".task-logs";
+pub const DEFAULT_SHELL: &str = "sh";
+const DEFAULT_KILL_GRACE: u64 = 5;
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Config {
     pub global: Option<GlobalConfig>,
     pub tasks: HashMap<String, Task>,
     pub parsers: Option<HashMap<String, Parser>>,
+    /// Directory of the config file, which the `working_dir`, `inputs`, `outputs` and
+    /// `watch_files` of tasks are relative to. Unset when the config was not read from a file
+    #[serde(skip)]
+    pub base_dir: Option<PathBuf>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -19,19 +32,73 @@ pub struct GlobalConfig {
     pub log_level: Option<String>,
     pub max_parallel: Option<u32>,
     pub output_dir: Option<String>,
+    /// Shell used to run task commands: a program such as `sh` or `bash`, or `none` to run
+    /// them directly
+    pub shell: Option<String>,
+    /// Seconds any task may run before it is terminated, unless the task sets its own
+    pub timeout: Option<u64>,
+    /// Seconds a task gets to exit after SIGTERM before it is killed with SIGKILL
+    pub kill_grace: Option<u64>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// The global config with a default for every setting `[global]` leaves out
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct GlobalSettings {
+    pub log_level: String,
+    pub max_parallel: u32,
+    pub output_dir: String,
+    pub shell: String,
+    pub timeout: Option<u64>,
+    pub kill_grace: u64,
+}
+
+/// The settings a task runs with, its own where it sets them and the global ones otherwise
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct TaskSettings {
+    pub shell: String,
+    pub timeout: Option<u64>,
+    pub kill_grace: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Task {
     pub command: String,
     pub description: Option<String>,
+    /// Labels to group tasks by, e.g. `ci` or `frontend`, used to filter `taskr list`
+    pub tags: Option<Vec<String>>,
     pub parsers: Option<Vec<String>>,
     pub watch_files: Option<Vec<String>>,
     pub depends_on: Option<Vec<String>>,
     pub auto_restart: Option<bool>,
     pub port_check: Option<u16>,
+    /// Parser match that marks the task as ready, either a level such as `success` or the level
+    /// of one parser such as `nextjs:success`
+    pub ready_on: Option<String>,
+    /// Seconds the task gets to pass its readiness checks before it is considered failed
+    pub ready_timeout: Option<u64>,
+    /// Milliseconds between attempts to connect to the `port_check` port
+    pub port_poll_interval: Option<u64>,
+    /// Globs of the files the task reads, the task is skipped while they and its command, env and
+    /// dependencies are unchanged since it last succeeded
+    pub inputs: Option<Vec<String>>,
+    /// Globs of the files the task creates, which have to exist for it to be skipped
+    pub outputs: Option<Vec<String>>,
     pub env: Option<HashMap<String, String>>,
     pub working_dir: Option<String>,
+    /// Overrides the global shell for this task
+    pub shell: Option<String>,
+    /// Seconds the task may run before it is terminated, overrides the global timeout
+    pub timeout: Option<u64>,
+    /// Overrides the global kill_grace for this task
+    pub kill_grace: Option<u64>,
+    /// How many times to run the task again after it failed
+    pub retries: Option<u32>,
+    /// Seconds to wait before running the task again
+    pub retry_delay: Option<u64>,
+    /// Double the retry delay after every attempt
+    pub retry_backoff: Option<bool>,
+    /// Only retry when a line matched one of these selectors, such as `yarn-install:error`
+    pub retry_on: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -44,6 +111,8 @@ pub struct Pattern {
     pub regex: String,
     pub level: String,
     pub extract: Option<String>,
+    /// What to do when the pattern matches: `fail`, `restart`, `notify`, `ignore` or
+    /// `run:<task>`
     pub action: Option<String>,
 }
 
@@ -61,8 +130,31 @@ pub enum ConfigError {
     #[error("Task '{task}' references unknown parser '{parser}'")]
     InvalidParser { task: String, parser: String },
 
-    #[error("Circular dependency detected involving task '{0}'")]
-    CircularDependency(String),
+    #[error("Parser '{parser}' has an invalid regex '{regex}': {error}")]
+    InvalidRegex {
+        parser: String,
+        regex: String,
+        error: regex::Error,
+    },
+
+    #[error("Task '{task}' has {field} '{selector}', which none of its parser patterns can match")]
+    InvalidMatchSelector {
+        task: String,
+        field: &'static str,
+        selector: String,
+    },
+
+    #[error("Parser '{parser}' has an unknown action '{action}'")]
+    InvalidAction { parser: String, action: String },
+
+    #[error("Parser '{parser}' has an action that runs unknown task '{task}'")]
+    InvalidActionTask { parser: String, task: String },
+
+    #[error("Task '{task}' runs itself again through a `run:` action of parser '{parser}'")]
+    RecursiveActionTask { task: String, parser: String },
+
+    #[error("{}", format_cycles(.0))]
+    CircularDependency(Vec<Vec<String>>),
 }
 
 impl Config {
@@ -70,7 +162,14 @@ impl Config {
         let content = fs::read_to_string(&path)
             .map_err(|e| ConfigError::FileRead(path.as_ref().to_path_buf(), e))?;
 
-        Self::load_from_string(&content)
+        let mut config = Self::load_from_string(&content)?;
+        config.base_dir = path
+            .as_ref()
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(Path::to_path_buf);
+
+        Ok(config)
     }
 
     pub fn load_from_string(content: &str) -> anyhow::Result<Self, ConfigError> {
@@ -82,7 +181,7 @@ impl Config {
     }
 
     /// validate that the configuration is valid
-    fn validate(&self) -> anyhow::Result<(), ConfigError> {
+    pub fn validate(&self) -> anyhow::Result<(), ConfigError> {
         // check that deps exist
         for (task_name, task) in &self.tasks {
             if let Some(deps) = &task.depends_on {
@@ -115,12 +214,197 @@ impl Config {
                 }
             }
 
-            // TODO: check for circular deps
+            // check that the readiness and retry selectors can be met
+            let selectors = task
+                .ready_on
+                .iter()
+                .map(|selector| ("ready_on", selector))
+                .chain(task.retry_on.iter().flatten().map(|s| ("retry_on", s)));
+
+            for (field, selector) in selectors {
+                if !self.task_can_match(task, &MatchSelector::parse(selector)) {
+                    return Err(ConfigError::InvalidMatchSelector {
+                        task: task_name.clone(),
+                        field,
+                        selector: selector.clone(),
+                    });
+                }
+            }
+        }
+
+        let cycles = self.find_cycles();
+        if !cycles.is_empty() {
+            return Err(ConfigError::CircularDependency(cycles));
+        }
+
+        // check that parser patterns compile
+        for (parser_name, parser) in self.parsers.iter().flatten() {
+            for pattern in &parser.patterns {
+                if let Err(error) = regex::Regex::new(&pattern.regex) {
+                    return Err(ConfigError::InvalidRegex {
+                        parser: parser_name.clone(),
+                        regex: pattern.regex.clone(),
+                        error,
+                    });
+                }
+
+                if let Some(action) = &pattern.action {
+                    match Action::parse(action) {
+                        None => {
+                            return Err(ConfigError::InvalidAction {
+                                parser: parser_name.clone(),
+                                action: action.clone(),
+                            });
+                        }
+                        Some(Action::Run(task)) if !self.tasks.contains_key(&task) => {
+                            return Err(ConfigError::InvalidActionTask {
+                                parser: parser_name.clone(),
+                                task,
+                            });
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+        }
+
+        // a hook that ends up running the task whose output fired it would never stop
+        for (task_name, task) in &self.tasks {
+            for (parser, hook) in self.get_hook_tasks(task) {
+                if self.hook_leads_to(&hook, task_name, &mut HashSet::new()) {
+                    return Err(ConfigError::RecursiveActionTask {
+                        task: task_name.clone(),
+                        parser: parser.clone(),
+                    });
+                }
+            }
         }
 
         Ok(())
     }
 
+    /// The tasks run by the `run:` actions of a task's parsers, with the parser of each
+    fn get_hook_tasks<'c>(&'c self, task: &'c Task) -> Vec<(&'c String, String)> {
+        task.parsers
+            .iter()
+            .flatten()
+            .filter_map(|name| self.parsers.as_ref()?.get_key_value(name))
+            .flat_map(|(name, parser)| {
+                parser.patterns.iter().filter_map(move |pattern| {
+                    match pattern.action.as_deref().and_then(Action::parse) {
+                        Some(Action::Run(hook)) => Some((name, hook)),
+                        _ => None,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Whether running `hook` ends up running `target`, through the hooks of the hooks
+    fn hook_leads_to(&self, hook: &str, target: &str, seen: &mut HashSet<String>) -> bool {
+        if hook == target {
+            return true;
+        }
+        if !seen.insert(hook.to_string()) {
+            return false;
+        }
+
+        self.get_task(hook).is_some_and(|task| {
+            self.get_hook_tasks(task)
+                .into_iter()
+                .any(|(_, next)| self.hook_leads_to(&next, target, seen))
+        })
+    }
+
+    /// Whether any pattern of the parsers a task uses is picked out by the selector
+    fn task_can_match(&self, task: &Task, selector: &MatchSelector) -> bool {
+        task.parsers.iter().flatten().any(|parser_name| {
+            self.parsers
+                .as_ref()
+                .and_then(|parsers| parsers.get(parser_name))
+                .is_some_and(|parser| {
+                    parser
+                        .patterns
+                        .iter()
+                        .any(|pattern| selector.accepts(parser_name, &pattern.level))
+                })
+        })
+    }
+
+    /// Find every dependency cycle in the task graph, each as the path of tasks that leads back
+    /// to its first task, e.g. `["a", "b", "c", "a"]`
+    fn find_cycles(&self) -> Vec<Vec<String>> {
+        let mut names: Vec<&str> = self.tasks.keys().map(String::as_str).collect();
+        names.sort();
+
+        let mut cycles = Vec::new();
+
+        for (i, &start) in names.iter().enumerate() {
+            // a cycle is found once, from its first task by name, so the search only goes
+            // through the tasks after `start` that can lead back to it
+            let leading_back = self.tasks_leading_to(start, &names[i + 1..]);
+            let mut path = vec![start];
+            self.visit_for_cycles(start, &leading_back, &mut path, &mut cycles);
+        }
+
+        cycles
+    }
+
+    /// Follow every path from the last task of `path` back to its first one, through tasks
+    /// in `allowed` that are not on the path yet
+    fn visit_for_cycles<'a>(
+        &'a self,
+        start: &str,
+        allowed: &HashSet<&'a str>,
+        path: &mut Vec<&'a str>,
+        cycles: &mut Vec<Vec<String>>,
+    ) {
+        let task_name = path[path.len() - 1];
+
+        for dep in self.task_deps(task_name) {
+            if dep == start {
+                let mut cycle: Vec<String> = path.iter().map(|t| t.to_string()).collect();
+                cycle.push(start.to_string());
+                cycles.push(cycle);
+            } else if allowed.contains(dep) && !path.contains(&dep) {
+                path.push(dep);
+                self.visit_for_cycles(start, allowed, path, cycles);
+                path.pop();
+            }
+        }
+    }
+
+    /// The candidates that depend on `target`, directly or through other candidates
+    fn tasks_leading_to<'a>(&'a self, target: &str, candidates: &[&'a str]) -> HashSet<&'a str> {
+        let mut leading = HashSet::new();
+
+        loop {
+            let before = leading.len();
+            for &candidate in candidates {
+                if !leading.contains(candidate)
+                    && self
+                        .task_deps(candidate)
+                        .any(|dep| dep == target || leading.contains(dep))
+                {
+                    leading.insert(candidate);
+                }
+            }
+
+            if leading.len() == before {
+                return leading;
+            }
+        }
+    }
+
+    fn task_deps<'a>(&'a self, task_name: &str) -> impl Iterator<Item = &'a str> {
+        self.tasks
+            .get(task_name)
+            .and_then(|t| t.depends_on.as_ref())
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
     /// find config file in current dir or in parents
     pub fn find_config_file() -> Option<PathBuf> {
         let valid_names = [
@@ -148,13 +432,39 @@ impl Config {
         None
     }
 
-    /// get the global configuration, if none is found then default to binary configuration
-    pub fn get_global_config(&self) -> GlobalConfig {
-        self.global.clone().unwrap_or_else(|| GlobalConfig {
-            log_level: Some("info".to_string()),
-            max_parallel: Some(4),
-            output_dir: Some(".task-logs".to_string()),
-        })
+    /// The global settings, each one falling back to its default when `[global]` doesn't set it
+    pub fn get_global_settings(&self) -> GlobalSettings {
+        let global = self.global.as_ref();
+
+        GlobalSettings {
+            log_level: global
+                .and_then(|g| g.log_level.clone())
+                .unwrap_or_else(|| DEFAULT_LOG_LEVEL.to_string()),
+            max_parallel: global
+                .and_then(|g| g.max_parallel)
+                .unwrap_or(DEFAULT_MAX_PARALLEL),
+            output_dir: global
+                .and_then(|g| g.output_dir.clone())
+                .unwrap_or_else(|| DEFAULT_OUTPUT_DIR.to_string()),
+            shell: global
+                .and_then(|g| g.shell.clone())
+                .unwrap_or_else(|| DEFAULT_SHELL.to_string()),
+            timeout: global.and_then(|g| g.timeout),
+            kill_grace: global
+                .and_then(|g| g.kill_grace)
+                .unwrap_or(DEFAULT_KILL_GRACE),
+        }
+    }
+
+    /// The settings the task runs with, where the task's own override the global ones
+    pub fn get_task_settings(&self, task: &Task) -> TaskSettings {
+        let global = self.get_global_settings();
+
+        TaskSettings {
+            shell: task.shell.clone().unwrap_or(global.shell),
+            timeout: task.timeout.or(global.timeout),
+            kill_grace: task.kill_grace.unwrap_or(global.kill_grace),
+        }
     }
 
     /// get all the tasks that dont depend on others, and run these first as these could be what
@@ -179,12 +489,27 @@ impl Config {
         self.tasks.get(name)
     }
 
+    /// The directory a task runs in: its `working_dir` relative to the config file, or the
+    /// directory of the config file itself
+    pub fn task_dir(&self, task: &Task) -> PathBuf {
+        let base_dir = self
+            .base_dir
+            .clone()
+            .or_else(|| current_dir().ok())
+            .unwrap_or_default();
+
+        match &task.working_dir {
+            Some(working_dir) => base_dir.join(working_dir.trim()),
+            None => base_dir,
+        }
+    }
+
     /// Get the execution order for a task and its dependencies
     pub fn get_exec_order(&self, task_name: &str) -> Result<Vec<String>, ConfigError> {
-        let mut visited = HashSet::new();
+        let mut path = Vec::new();
         let mut order = Vec::new();
 
-        self.resolve_dependencies(task_name, &mut visited, &mut order)?;
+        self.resolve_dependencies(task_name, &mut path, &mut order)?;
         Ok(order)
     }
 
@@ -192,11 +517,13 @@ impl Config {
     fn resolve_dependencies(
         &self,
         task_name: &str,
-        visited: &mut HashSet<String>,
+        path: &mut Vec<String>,
         order: &mut Vec<String>,
     ) -> Result<(), ConfigError> {
-        if visited.contains(task_name) {
-            return Err(ConfigError::CircularDependency(task_name.to_string()));
+        if let Some(start) = path.iter().position(|t| t == task_name) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(task_name.to_string());
+            return Err(ConfigError::CircularDependency(vec![cycle]));
         }
 
         let task = self
@@ -207,13 +534,13 @@ impl Config {
                 dependency: task_name.to_string(),
             })?;
 
-        visited.insert(task_name.to_string());
+        path.push(task_name.to_string());
 
         // Resolve all the dependencies of this task
         if let Some(deps) = &task.depends_on {
             for dep in deps {
                 if !order.contains(dep) {
-                    self.resolve_dependencies(dep, visited, order)?;
+                    self.resolve_dependencies(dep, path, order)?;
                 }
             }
         }
@@ -223,24 +550,95 @@ impl Config {
             order.push(task_name.to_string());
         }
 
-        visited.remove(task_name);
+        path.pop();
 
         Ok(())
     }
 
+    /// Put a set of tasks in execution order without duplicates, each task after those of its
+    /// dependencies that are part of the set
+    pub fn order_tasks(&self, tasks: &[String]) -> Result<Vec<String>, ConfigError> {
+        let mut order: Vec<String> = Vec::new();
+
+        for task in tasks {
+            for name in self.get_exec_order(task)? {
+                if tasks.contains(&name) && !order.contains(&name) {
+                    order.push(name);
+                }
+            }
+        }
+
+        Ok(order)
+    }
+
+    /// Get every task that depends on the given task, directly or through other tasks
+    pub fn get_all_dependents(&self, task_name: &str) -> Vec<&String> {
+        let mut found: Vec<&String> = Vec::new();
+        let mut queue = vec![task_name];
+
+        while let Some(current) = queue.pop() {
+            for dependent in self.get_dependent_tasks(current) {
+                if !found.contains(&dependent) {
+                    found.push(dependent);
+                    queue.push(dependent);
+                }
+            }
+        }
+
+        found.sort();
+        found
+    }
+
+    /// Get the execution order of a task followed by everything downstream of it. Other
+    /// dependencies of the downstream tasks are left out, as they are not affected by the task
+    pub fn get_dependents_exec_order(&self, task_name: &str) -> Result<Vec<String>, ConfigError> {
+        if !self.has_task(task_name) {
+            return Err(ConfigError::InvalidDependency {
+                task: "requested".to_string(),
+                dependency: task_name.to_string(),
+            });
+        }
+
+        let dependents = self.get_all_dependents(task_name);
+        let mut order = vec![task_name.to_string()];
+
+        // the exec order of each dependent lists its dependencies first, so merging them keeps
+        // every task after the tasks it depends on
+        for dependent in &dependents {
+            for name in self.get_exec_order(dependent)? {
+                if dependents.contains(&&name) && !order.contains(&name) {
+                    order.push(name);
+                }
+            }
+        }
+
+        Ok(order)
+    }
+
     pub fn get_dependent_tasks(&self, task_name: &str) -> Vec<&String> {
         self.tasks
             .iter()
             .filter(|(_, task)| {
                 task.depends_on
                     .as_ref()
-                    .map_or(false, |deps| deps.contains(&task_name.to_string()))
+                    .is_some_and(|deps| deps.contains(&task_name.to_string()))
             })
             .map(|(name, _)| name)
             .collect()
     }
 }
 
+/// Render cycles as `a -> b -> c -> a`
+fn format_cycles(cycles: &[Vec<String>]) -> String {
+    let cycles: Vec<String> = cycles.iter().map(|cycle| cycle.join(" -> ")).collect();
+
+    if cycles.len() == 1 {
+        format!("Circular dependency detected: {}", cycles[0])
+    } else {
+        format!("Circular dependencies detected: {}", cycles.join("; "))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -283,6 +681,40 @@ patterns = [
         assert_eq!(dev_task.depends_on.as_ref().unwrap(), &vec!["install"]);
     }
 
+    #[test]
+    fn test_settings_default_per_field() {
+        let config = Config::load_from_string(
+            r#"
+[global]
+max_parallel = 2
+timeout = 60
+
+[tasks.install]
+command = "yarn install"
+
+[tasks.dev]
+command = "yarn dev"
+shell = "bash"
+kill_grace = 1
+        "#,
+        )
+        .unwrap();
+
+        let global = config.get_global_settings();
+        assert_eq!(global.max_parallel, 2);
+        assert_eq!(global.output_dir, DEFAULT_OUTPUT_DIR);
+        assert_eq!(global.kill_grace, DEFAULT_KILL_GRACE);
+
+        let install = config.get_task_settings(&config.tasks["install"]);
+        assert_eq!(install.shell, DEFAULT_SHELL);
+        assert_eq!(install.timeout, Some(60));
+        assert_eq!(install.kill_grace, DEFAULT_KILL_GRACE);
+
+        let dev = config.get_task_settings(&config.tasks["dev"]);
+        assert_eq!(dev.shell, "bash");
+        assert_eq!(dev.kill_grace, 1);
+    }
+
     #[test]
     fn test_invalid_dependency() {
         let toml_content = r#"
@@ -299,6 +731,215 @@ depends_on = ["nonexistent"]
         ));
     }
 
+    #[test]
+    fn test_circular_dependencies() {
+        let toml_content = r#"
+[tasks.a]
+command = "echo a"
+depends_on = ["b"]
+
+[tasks.b]
+command = "echo b"
+depends_on = ["c"]
+
+[tasks.c]
+command = "echo c"
+depends_on = ["a"]
+
+[tasks.d]
+command = "echo d"
+depends_on = ["d"]
+
+[tasks.e]
+command = "echo e"
+depends_on = ["a"]
+
+[tasks.f]
+command = "echo f"
+depends_on = ["g", "h"]
+
+[tasks.g]
+command = "echo g"
+depends_on = ["h"]
+
+[tasks.h]
+command = "echo h"
+depends_on = ["f"]
+        "#;
+
+        let err = Config::load_from_string(toml_content).unwrap_err();
+        let ConfigError::CircularDependency(cycles) = &err else {
+            panic!("expected a circular dependency error, got {err}");
+        };
+
+        assert_eq!(
+            cycles,
+            &vec![
+                vec!["a", "b", "c", "a"],
+                vec!["d", "d"],
+                vec!["f", "g", "h", "f"],
+                vec!["f", "h", "f"],
+            ]
+        );
+        assert_eq!(
+            err.to_string(),
+            "Circular dependencies detected: a -> b -> c -> a; d -> d; f -> g -> h -> f; f -> h -> f"
+        );
+    }
+
+    #[test]
+    fn test_invalid_ready_on() {
+        let toml_content = r#"
+[tasks.dev]
+command = "yarn dev"
+parsers = ["nextjs"]
+ready_on = "nx-serve:success"
+
+[parsers.nextjs]
+patterns = [{ regex = "ready - started server on.+", level = "success" }]
+        "#;
+
+        let result = Config::load_from_string(toml_content);
+        assert!(matches!(
+            result.unwrap_err(),
+            ConfigError::InvalidMatchSelector {
+                field: "ready_on",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_invalid_action() {
+        let toml_content = r#"
+[tasks.dev]
+command = "webpack serve"
+parsers = ["webpack"]
+
+[parsers.webpack]
+patterns = [{ regex = "Module not found", level = "error", action = "run:install" }]
+        "#;
+
+        let result = Config::load_from_string(toml_content);
+        assert!(matches!(
+            result.unwrap_err(),
+            ConfigError::InvalidActionTask { .. }
+        ));
+    }
+
+    #[test]
+    fn test_recursive_action_task() {
+        let direct = r#"
+[tasks.dev]
+command = "webpack serve"
+parsers = ["webpack"]
+
+[parsers.webpack]
+patterns = [{ regex = "Compiled", level = "info", action = "run:dev" }]
+        "#;
+
+        let through_other_hook = r#"
+[tasks.dev]
+command = "webpack serve"
+parsers = ["webpack"]
+
+[tasks.codegen]
+command = "yarn codegen"
+parsers = ["codegen"]
+
+[parsers.webpack]
+patterns = [{ regex = "Schema changed", level = "info", action = "run:codegen" }]
+
+[parsers.codegen]
+patterns = [{ regex = "Generated", level = "info", action = "run:dev" }]
+        "#;
+
+        for toml_content in [direct, through_other_hook] {
+            let result = Config::load_from_string(toml_content);
+            assert!(matches!(
+                result.unwrap_err(),
+                ConfigError::RecursiveActionTask { .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_invalid_parser_regex() {
+        let toml_content = r#"
+[tasks.build]
+command = "yarn build"
+parsers = ["broken"]
+
+[parsers.broken]
+patterns = [{ regex = "Found (\\d+ error", level = "error" }]
+        "#;
+
+        let result = Config::load_from_string(toml_content);
+        assert!(matches!(
+            result.unwrap_err(),
+            ConfigError::InvalidRegex { .. }
+        ));
+    }
+
+    #[test]
+    fn test_get_dependents_exec_order() {
+        let toml_content = r#"
+[tasks.install]
+command = "yarn install"
+
+[tasks.codegen]
+command = "yarn codegen"
+
+[tasks.build]
+command = "yarn build"
+depends_on = ["codegen", "install"]
+
+[tasks.test]
+command = "yarn test"
+depends_on = ["build"]
+
+[tasks.e2e]
+command = "yarn e2e"
+depends_on = ["test", "build"]
+        "#;
+
+        let config = Config::load_from_string(toml_content).unwrap();
+
+        assert_eq!(
+            config.get_all_dependents("install"),
+            vec!["build", "e2e", "test"]
+        );
+        assert_eq!(
+            config.get_dependents_exec_order("install").unwrap(),
+            vec!["install", "build", "test", "e2e"]
+        );
+    }
+
+    #[test]
+    fn test_order_tasks_of_multiple_targets() {
+        let toml_content = r#"
+[tasks.install]
+command = "yarn install"
+
+[tasks.build]
+command = "yarn build"
+depends_on = ["install"]
+
+[tasks.lint]
+command = "yarn lint"
+depends_on = ["install"]
+        "#;
+
+        let config = Config::load_from_string(toml_content).unwrap();
+
+        let mut tasks = config.get_exec_order("lint").unwrap();
+        tasks.extend(config.get_exec_order("build").unwrap());
+        assert_eq!(
+            config.order_tasks(&tasks).unwrap(),
+            vec!["install", "lint", "build"]
+        );
+    }
+
     #[test]
     fn test_get_root_tasks() {
         let toml_content = r#"
@@ -319,4 +960,32 @@ command = "yarn test"
 
         assert_eq!(root_tasks, vec!["install", "test"]);
     }
+
+    #[test]
+    fn test_task_dir_is_relative_to_config_file() {
+        let dir = crate::logs::test_util::temp_dir("task-dir");
+        let path = dir.join("taskr.toml");
+        fs::write(
+            &path,
+            r#"
+[tasks.web]
+command = "yarn dev"
+working_dir = "web/ "
+
+[tasks.lint]
+command = "yarn lint"
+            "#,
+        )
+        .unwrap();
+
+        let config = Config::load_from_file(&path).unwrap();
+        assert_eq!(config.base_dir.as_deref(), Some(dir.as_path()));
+        assert_eq!(
+            config.task_dir(config.get_task("web").unwrap()),
+            dir.join("web")
+        );
+        assert_eq!(config.task_dir(config.get_task("lint").unwrap()), dir);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
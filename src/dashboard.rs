@@ -0,0 +1,604 @@
+use crate::config::Task;
+use crate::output::{self, Captured, Stream};
+use crate::runner::{RunContext, StopRequests, Stopped};
+use crate::scheduler::{Scheduler, TaskRunner};
+use crate::shutdown::{self, Interrupted};
+use console::Term;
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+};
+use std::{
+    cell::Cell,
+    collections::{HashSet, VecDeque},
+    ops::Range,
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver},
+    },
+    thread::{self, Scope, ScopedJoinHandle},
+    time::Duration,
+};
+
+/// Lines of output kept per task, older lines are dropped
+const MAX_LINES: usize = 5000;
+
+/// How long to wait for a key press before drawing the output that came in meanwhile
+const TICK: Duration = Duration::from_millis(100);
+
+/// How long a crashed `auto_restart` task waits before it is started again
+const AUTO_RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// Lines scrolled by PgUp and PgDn
+const PAGE: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TaskState {
+    Pending,
+    Running,
+    Ready,
+    Restarting,
+    Done,
+    Failed,
+    Stopped,
+    /// Started by a `run:<task>` action instead of the scheduler
+    Hook,
+}
+
+impl TaskState {
+    fn label(self) -> &'static str {
+        match self {
+            TaskState::Pending => "pending",
+            TaskState::Running => "running",
+            TaskState::Ready => "ready",
+            TaskState::Restarting => "restarting",
+            TaskState::Done => "done",
+            TaskState::Failed => "failed",
+            TaskState::Stopped => "stopped",
+            TaskState::Hook => "hook",
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            TaskState::Pending => "○",
+            TaskState::Running => "◐",
+            TaskState::Ready => "●",
+            TaskState::Restarting => "↻",
+            TaskState::Done => "✔",
+            TaskState::Failed => "✖",
+            TaskState::Stopped => "■",
+            TaskState::Hook => "🪝",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            TaskState::Pending | TaskState::Stopped | TaskState::Hook => Color::DarkGray,
+            TaskState::Running => Color::Cyan,
+            TaskState::Ready | TaskState::Done => Color::Green,
+            TaskState::Restarting => Color::Yellow,
+            TaskState::Failed => Color::Red,
+        }
+    }
+
+    /// Whether the task has a process that can be stopped or restarted
+    fn is_active(self) -> bool {
+        matches!(self, TaskState::Running | TaskState::Ready)
+    }
+}
+
+struct LogLine {
+    stream: Stream,
+    text: String,
+    /// Level of the parser pattern the line matched
+    level: Option<String>,
+    /// Reported by taskr rather than printed by the task
+    status: bool,
+}
+
+/// Everything the dashboard shows about one task
+struct TaskView {
+    name: String,
+    state: TaskState,
+    lines: VecDeque<LogLine>,
+    warnings: usize,
+    errors: usize,
+}
+
+impl TaskView {
+    fn new(name: &str, state: TaskState) -> Self {
+        Self {
+            name: name.to_string(),
+            state,
+            lines: VecDeque::new(),
+            warnings: 0,
+            errors: 0,
+        }
+    }
+
+    fn push(&mut self, line: LogLine) {
+        match line.level.as_deref() {
+            Some("warn" | "warning") => self.warnings += 1,
+            Some("error") => self.errors += 1,
+            _ => {}
+        }
+
+        if self.lines.len() == MAX_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    /// The lines that fit in `height` rows, `scroll` lines up from the bottom
+    fn visible(&self, height: usize, scroll: usize) -> Range<usize> {
+        let end = self.lines.len() - scroll.min(self.lines.len().saturating_sub(height));
+        end.saturating_sub(height)..end
+    }
+}
+
+/// What is selected and shown, only touched by the thread drawing the dashboard
+#[derive(Default)]
+struct View {
+    selected: usize,
+    /// Show the log of the selected task over the full width
+    focused: bool,
+    /// Lines scrolled up from the bottom of the log, 0 follows new output
+    scroll: usize,
+    /// Whether the scheduler is done with all tasks
+    finished: bool,
+    quitting: bool,
+}
+
+/// Runs tasks for the scheduler like `RunContext` does, keeping track of their state for the
+/// dashboard, and runs them again when they are restarted
+struct Dashboard<'a> {
+    ctx: RunContext<'a>,
+    tasks: Mutex<Vec<TaskView>>,
+    /// Tasks that are being stopped to be started again
+    restarts: Mutex<HashSet<String>>,
+}
+
+impl TaskRunner for Dashboard<'_> {
+    fn run(&self, name: &str, task: &Task, on_ready: &dyn Fn()) -> anyhow::Result<()> {
+        let signalled = Cell::new(false);
+
+        loop {
+            self.set_state(name, TaskState::Running);
+            let result = self.ctx.run(name, task, &|| {
+                self.set_state(name, TaskState::Ready);
+                // dependents only have to wait for the first run to become ready
+                if !signalled.replace(true) {
+                    on_ready();
+                }
+            });
+
+            if self.restarts.lock().unwrap().remove(name) && !shutdown::requested() {
+                self.ctx.stops.withdraw(name);
+                continue;
+            }
+
+            let crashed = result
+                .as_ref()
+                .is_err_and(|e| !e.is::<Interrupted>() && !e.is::<Stopped>());
+            if crashed && task.auto_restart == Some(true) && !self.ctx.stops.is_requested(name) {
+                self.set_state(name, TaskState::Restarting);
+                output::status_err(
+                    name,
+                    format!(
+                        "🔄 Restarting task '{}' in {}s",
+                        name,
+                        AUTO_RESTART_DELAY.as_secs()
+                    ),
+                );
+                thread::sleep(AUTO_RESTART_DELAY);
+
+                if !self.ctx.stops.is_requested(name) {
+                    continue;
+                }
+            }
+
+            self.set_state(
+                name,
+                match &result {
+                    Err(e) if !e.is::<Interrupted>() && !e.is::<Stopped>() => TaskState::Failed,
+                    Ok(()) if !self.ctx.stops.is_requested(name) => TaskState::Done,
+                    _ => TaskState::Stopped,
+                },
+            );
+            return result;
+        }
+    }
+
+    fn stop(&self, name: &str) {
+        self.ctx.stop(name);
+    }
+
+    fn interrupted(&self) -> bool {
+        self.ctx.interrupted()
+    }
+}
+
+impl<'a> Dashboard<'a> {
+    fn new(ctx: RunContext<'a>, tasks: &[String]) -> Self {
+        Self {
+            ctx,
+            tasks: Mutex::new(
+                tasks
+                    .iter()
+                    .map(|name| TaskView::new(name, TaskState::Pending))
+                    .collect(),
+            ),
+            restarts: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn state(&self, name: &str) -> Option<TaskState> {
+        let tasks = self.tasks.lock().unwrap();
+        tasks.iter().find(|t| t.name == name).map(|t| t.state)
+    }
+
+    fn set_state(&self, name: &str, state: TaskState) {
+        self.update(name, |view| view.state = state);
+    }
+
+    /// Change the view of a task, adding it when a hook printed something for it
+    fn update(&self, name: &str, f: impl FnOnce(&mut TaskView)) {
+        let mut tasks = self.tasks.lock().unwrap();
+        match tasks.iter_mut().find(|t| t.name == name) {
+            Some(view) => f(view),
+            None => {
+                let mut view = TaskView::new(name, TaskState::Hook);
+                f(&mut view);
+                tasks.push(view);
+            }
+        }
+    }
+
+    fn record(&self, captured: Captured) {
+        let (task, stream, line, level, status) = match captured {
+            Captured::Output {
+                task,
+                stream,
+                line,
+                level,
+            } => (task, stream, line, level, false),
+            Captured::Status { task, stream, line } => (task, stream, line, None, true),
+        };
+
+        self.update(&task, |view| {
+            // the summary after a run comes in as one status with several lines
+            for text in plain(&line).split('\n') {
+                view.push(LogLine {
+                    stream,
+                    text: text.to_string(),
+                    level: level.clone(),
+                    status,
+                });
+            }
+        });
+    }
+
+    /// Stop a running task and start it again, or run a finished task again
+    fn restart<'scope, 'env>(&'env self, scope: &'scope Scope<'scope, 'env>, name: &str) {
+        if shutdown::requested() {
+            return;
+        }
+
+        // inputs may have been edited since the key was worked out
+        if let Some(cache) = self.ctx.cache {
+            cache.forget(name);
+        }
+
+        match self.state(name) {
+            Some(state) if state.is_active() => {
+                self.restarts.lock().unwrap().insert(name.to_string());
+                self.set_state(name, TaskState::Restarting);
+                self.ctx.stop(name);
+            }
+            Some(TaskState::Done | TaskState::Failed | TaskState::Stopped) => {
+                let Some(task) = self.ctx.config.get_task(name) else {
+                    return;
+                };
+                let name = name.to_string();
+
+                self.ctx.stops.withdraw(&name);
+                self.set_state(&name, TaskState::Restarting);
+                scope.spawn(move || {
+                    // the outcome shows in the sidebar
+                    let _ = self.run(&name, task, &|| {});
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Draw the dashboard and handle keys until it is closed, and every task has stopped
+    fn show<'scope, 'env>(
+        &'env self,
+        scope: &'scope Scope<'scope, 'env>,
+        terminal: &mut DefaultTerminal,
+        captured: &Receiver<Captured>,
+        scheduler: &ScopedJoinHandle<'scope, anyhow::Result<()>>,
+    ) -> anyhow::Result<()> {
+        let mut view = View::default();
+
+        loop {
+            for captured in captured.try_iter() {
+                self.record(captured);
+            }
+            view.finished = scheduler.is_finished();
+
+            let active = {
+                let tasks = self.tasks.lock().unwrap();
+                tasks.iter().any(|t| t.state.is_active())
+            };
+            if view.quitting && view.finished && !active {
+                return Ok(());
+            }
+
+            terminal.draw(|frame| self.draw(frame, &mut view))?;
+
+            if !event::poll(TICK)? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            let selected = {
+                let tasks = self.tasks.lock().unwrap();
+                tasks[view.selected].name.clone()
+            };
+
+            match key.code {
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.quit(&mut view)
+                }
+                KeyCode::Char('q') => self.quit(&mut view),
+                KeyCode::Up | KeyCode::Char('k') => {
+                    view.selected = view.selected.saturating_sub(1);
+                    view.scroll = 0;
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    let count = self.tasks.lock().unwrap().len();
+                    view.selected = (view.selected + 1).min(count - 1);
+                    view.scroll = 0;
+                }
+                KeyCode::Enter | KeyCode::Char('f') => view.focused = !view.focused,
+                KeyCode::Esc => view.focused = false,
+                KeyCode::Char('r') => self.restart(scope, &selected),
+                KeyCode::Char('s') => self.stop(&selected),
+                KeyCode::PageUp => view.scroll += PAGE,
+                KeyCode::PageDown => view.scroll = view.scroll.saturating_sub(PAGE),
+                KeyCode::Home | KeyCode::Char('g') => view.scroll = usize::MAX,
+                KeyCode::End | KeyCode::Char('G') => view.scroll = 0,
+                _ => {}
+            }
+        }
+    }
+
+    /// Ask every task to shut down, or kill them all when that was asked already
+    fn quit(&self, view: &mut View) {
+        if view.quitting {
+            ratatui::restore();
+            eprintln!("💀 Killing all tasks");
+            shutdown::kill_all();
+        }
+
+        view.quitting = true;
+        shutdown::request();
+    }
+
+    fn draw(&self, frame: &mut Frame, view: &mut View) {
+        let tasks = self.tasks.lock().unwrap();
+        let [main, footer] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+
+        let log = if view.focused {
+            main
+        } else {
+            let width = tasks.iter().map(|t| t.name.len()).max().unwrap_or(0);
+            let [sidebar, log] = Layout::horizontal([
+                Constraint::Length((width as u16 + 30).min(main.width / 2)),
+                Constraint::Min(0),
+            ])
+            .areas(main);
+
+            draw_sidebar(frame, sidebar, &tasks, width, view.selected);
+            log
+        };
+
+        let task = &tasks[view.selected];
+        // keep the scroll position within the log, so scrolling back down responds right away
+        let height = log.height.saturating_sub(2) as usize;
+        view.scroll = view.scroll.min(task.lines.len().saturating_sub(height));
+        draw_log(frame, log, task, view.scroll);
+
+        draw_footer(frame, footer, &tasks, view);
+    }
+}
+
+/// Run the tasks with a dashboard of their state and output instead of printing it, which stays
+/// open after they finished until it is closed
+pub fn run(scheduler: &Scheduler, tasks: &[String]) -> anyhow::Result<()> {
+    if !Term::stdout().is_term() {
+        return Err(anyhow::anyhow!("The dashboard needs to run in a terminal"));
+    }
+
+    let stops = StopRequests::default();
+    let dashboard = Dashboard::new(scheduler.context(tasks, &stops), tasks);
+
+    let (tx, rx) = mpsc::channel();
+    output::capture(tx);
+    let mut terminal = ratatui::try_init()?;
+
+    let result = thread::scope(|scope| {
+        let run = scope.spawn(|| scheduler.run_with(tasks, &dashboard));
+        let shown = dashboard.show(scope, &mut terminal, &rx, &run);
+
+        if shown.is_err() {
+            shutdown::request();
+        }
+        let result = run
+            .join()
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("The scheduler panicked")));
+
+        shown.and(result)
+    });
+
+    ratatui::restore();
+    output::release();
+
+    result
+}
+
+fn draw_sidebar(frame: &mut Frame, area: Rect, tasks: &[TaskView], width: usize, selected: usize) {
+    let items: Vec<ListItem> = tasks
+        .iter()
+        .map(|task| {
+            let style = Style::new().fg(task.state.color());
+            let mut spans = vec![
+                Span::styled(format!("{} ", task.state.symbol()), style),
+                Span::raw(format!("{:<width$} ", task.name, width = width)),
+                Span::styled(format!("{:<10}", task.state.label()), style),
+            ];
+
+            if task.warnings > 0 {
+                spans.push(Span::styled(
+                    format!(" ⚠ {}", task.warnings),
+                    Style::new().fg(Color::Yellow),
+                ));
+            }
+            if task.errors > 0 {
+                spans.push(Span::styled(
+                    format!(" ✖ {}", task.errors),
+                    Style::new().fg(Color::Red),
+                ));
+            }
+
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(Block::bordered().title(" Tasks "))
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+
+    let mut state = ListState::default().with_selected(Some(selected));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_log(frame: &mut Frame, area: Rect, task: &TaskView, scroll: usize) {
+    let height = area.height.saturating_sub(2) as usize;
+
+    let lines: Vec<Line> = task
+        .lines
+        .range(task.visible(height, scroll))
+        .map(|line| {
+            let (gutter, gutter_style) = match (line.status, line.stream) {
+                (true, _) => ("»", Style::new().fg(Color::Blue)),
+                (false, Stream::Stdout) => ("│", Style::new().fg(Color::DarkGray)),
+                (false, Stream::Stderr) => ("!", Style::new().fg(Color::Red)),
+            };
+            let style = match line.level.as_deref() {
+                Some("error") => Style::new().fg(Color::Red),
+                Some("warn" | "warning") => Style::new().fg(Color::Yellow),
+                Some("success") => Style::new().fg(Color::Green),
+                Some("info") => Style::new().fg(Color::Cyan),
+                _ if line.status => Style::new().add_modifier(Modifier::BOLD),
+                _ => Style::new(),
+            };
+
+            Line::from(vec![
+                Span::styled(format!("{} ", gutter), gutter_style),
+                Span::styled(line.text.as_str(), style),
+            ])
+        })
+        .collect();
+
+    let mut title = format!(" {} · {} ", task.name, task.state.label());
+    if scroll > 0 {
+        title.push_str(&format!("· ↑ {} lines ", scroll));
+    }
+
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(title)),
+        area,
+    );
+}
+
+fn draw_footer(frame: &mut Frame, area: Rect, tasks: &[TaskView], view: &View) {
+    let warnings: usize = tasks.iter().map(|t| t.warnings).sum();
+    let errors: usize = tasks.iter().map(|t| t.errors).sum();
+
+    let message = if view.quitting && !view.finished {
+        " Shutting down, press q again to kill all tasks ".to_string()
+    } else if view.finished {
+        " All tasks finished · r restart · q quit ".to_string()
+    } else {
+        " ↑↓ select · ⏎ focus · r restart · s stop · PgUp/PgDn scroll · q quit ".to_string()
+    };
+
+    let line = Line::from(vec![
+        Span::styled(message, Style::new().add_modifier(Modifier::REVERSED)),
+        Span::styled(format!(" ⚠ {}", warnings), Style::new().fg(Color::Yellow)),
+        Span::styled(format!(" ✖ {}", errors), Style::new().fg(Color::Red)),
+    ]);
+    frame.render_widget(Paragraph::new(line), area);
+}
+
+/// The text of a line without the colors and bells meant for the terminal
+fn plain(line: &str) -> String {
+    console::strip_ansi_codes(line)
+        .replace('\x07', "")
+        .replace('\t', "    ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_line(text: &str, level: Option<&str>) -> LogLine {
+        LogLine {
+            stream: Stream::Stdout,
+            text: text.to_string(),
+            level: level.map(str::to_string),
+            status: false,
+        }
+    }
+
+    #[test]
+    fn test_counts_parser_levels() {
+        let mut view = TaskView::new("web", TaskState::Running);
+        view.push(log_line("compiling", None));
+        view.push(log_line("deprecated", Some("warn")));
+        view.push(log_line("Module not found", Some("error")));
+        view.push(log_line("Type error", Some("error")));
+
+        assert_eq!(view.warnings, 1);
+        assert_eq!(view.errors, 2);
+        assert_eq!(view.lines.len(), 4);
+    }
+
+    #[test]
+    fn test_visible_lines_follow_scroll() {
+        let mut view = TaskView::new("web", TaskState::Running);
+        for i in 0..20 {
+            view.push(log_line(&i.to_string(), None));
+        }
+
+        assert_eq!(view.visible(5, 0), 15..20);
+        assert_eq!(view.visible(5, 3), 12..17);
+        // scrolling stops at the first line
+        assert_eq!(view.visible(5, 100), 0..5);
+        assert_eq!(view.visible(50, 0), 0..20);
+    }
+}
@@ -0,0 +1,335 @@
+use crate::config::{Config, ConfigError, Parser, Task};
+use serde::Serialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table, TableLike, Value};
+
+/// A config file that is changed in place, so the comments, key order and formatting of
+/// everything that is not changed stay as they were written
+pub struct ConfigDocument {
+    path: PathBuf,
+    doc: DocumentMut,
+}
+
+impl ConfigDocument {
+    /// Read the config file, or start an empty one if it doesn't exist yet
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(anyhow::anyhow!(
+                    "Failed to read '{}': {}",
+                    path.display(),
+                    e
+                ));
+            }
+        };
+
+        Self::parse(path, &content)
+    }
+
+    /// A config file with this content, to be written to `path`
+    pub fn parse(path: &Path, content: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            doc: content
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Failed to parse '{}': {}", path.display(), e))?,
+        })
+    }
+
+    pub fn has_parser(&self, name: &str) -> bool {
+        self.doc
+            .get("parsers")
+            .and_then(Item::as_table_like)
+            .is_some_and(|parsers| parsers.contains_key(name))
+    }
+
+    /// Add the task as a `[tasks.<name>]` table after everything else in the file
+    pub fn insert_task(&mut self, name: &str, task: &Task) -> anyhow::Result<()> {
+        let table = to_table(task)?;
+        section(&mut self.doc, "tasks")?.insert(name, Item::Table(table));
+        Ok(())
+    }
+
+    /// Change a task in place. Only the keys whose value changed are written again, and a
+    /// renamed task keeps its place in the file
+    pub fn update_task(
+        &mut self,
+        name: &str,
+        old: &Task,
+        new_name: &str,
+        new: &Task,
+    ) -> anyhow::Result<()> {
+        let old_values = toml::Table::try_from(old)?;
+        let new_values = toml::Table::try_from(new)?;
+        let mut new_table = to_table(new)?;
+
+        let tasks = section(&mut self.doc, "tasks")?;
+        let table = tasks
+            .get_mut(name)
+            .and_then(Item::as_table_like_mut)
+            .ok_or_else(|| anyhow::anyhow!("Task '{}' not found in the config file", name))?;
+
+        for key in old_values.keys() {
+            if !new_values.contains_key(key) {
+                table.remove(key);
+            }
+        }
+        for (key, value) in &new_values {
+            if old_values.get(key) != Some(value)
+                && let Some(item) = new_table.remove(key)
+            {
+                table.insert(key, item);
+            }
+        }
+
+        if new_name != name
+            && let Some(item) = tasks.remove(name)
+        {
+            tasks.insert(new_name, item);
+        }
+
+        Ok(())
+    }
+
+    /// Point the `depends_on` of other tasks and the `run:<task>` actions of parsers at the new
+    /// name of a renamed task
+    pub fn rename_references(&mut self, name: &str, new_name: &str) {
+        let tasks = self.doc.get_mut("tasks").and_then(Item::as_table_like_mut);
+        for (_, task) in tasks.into_iter().flat_map(|tasks| tasks.iter_mut()) {
+            let depends_on = task
+                .as_table_like_mut()
+                .and_then(|task| task.get_mut("depends_on"))
+                .and_then(Item::as_array_mut);
+
+            for dep in depends_on.into_iter().flat_map(|deps| deps.iter_mut()) {
+                if dep.as_str() == Some(name) {
+                    replace_str(dep, new_name);
+                }
+            }
+        }
+
+        let (action, new_action) = (format!("run:{}", name), format!("run:{}", new_name));
+        let parsers = self
+            .doc
+            .get_mut("parsers")
+            .and_then(Item::as_table_like_mut);
+        for (_, parser) in parsers.into_iter().flat_map(|parsers| parsers.iter_mut()) {
+            let Some(patterns) = parser
+                .as_table_like_mut()
+                .and_then(|parser| parser.get_mut("patterns"))
+            else {
+                continue;
+            };
+
+            let rename = |pattern: &mut dyn TableLike| {
+                if let Some(value) = pattern.get_mut("action").and_then(Item::as_value_mut)
+                    && value.as_str() == Some(action.as_str())
+                {
+                    replace_str(value, &new_action);
+                }
+            };
+
+            match patterns {
+                Item::ArrayOfTables(patterns) => patterns.iter_mut().for_each(|p| rename(p)),
+                Item::Value(Value::Array(patterns)) => patterns
+                    .iter_mut()
+                    .filter_map(Value::as_inline_table_mut)
+                    .for_each(|p| rename(p)),
+                _ => {}
+            }
+        }
+    }
+
+    /// Remove a task. Returns false when the file doesn't have it
+    pub fn remove_task(&mut self, name: &str) -> anyhow::Result<bool> {
+        let tasks = section(&mut self.doc, "tasks")?;
+        let removed = tasks.remove(name).is_some();
+
+        // the config needs a tasks table, even an empty one
+        if tasks.is_empty() {
+            tasks.set_implicit(false);
+        }
+
+        Ok(removed)
+    }
+
+    pub fn remove_parser(&mut self, name: &str) {
+        if let Some(parsers) = self
+            .doc
+            .get_mut("parsers")
+            .and_then(Item::as_table_like_mut)
+        {
+            parsers.remove(name);
+        }
+    }
+
+    /// Add the parser with a `[[parsers.<name>.patterns]]` table per pattern
+    pub fn insert_parser(&mut self, name: &str, parser: &Parser) -> anyhow::Result<()> {
+        let mut patterns = ArrayOfTables::new();
+        for pattern in &parser.patterns {
+            patterns.push(to_table(pattern)?);
+        }
+
+        let mut table = Table::new();
+        table.set_implicit(true);
+        table.insert("patterns", Item::ArrayOfTables(patterns));
+
+        section(&mut self.doc, "parsers")?.insert(name, Item::Table(table));
+        Ok(())
+    }
+
+    /// Check that the file, with the changes made to it, is a valid configuration
+    pub fn validate(&self) -> anyhow::Result<(), ConfigError> {
+        let config: Config =
+            toml::from_str(&self.doc.to_string()).map_err(ConfigError::ParseError)?;
+        config.validate()
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        fs::write(&self.path, self.doc.to_string())
+            .map_err(|e| anyhow::anyhow!("Failed to write '{}': {}", self.path.display(), e))
+    }
+}
+
+/// The top-level table with this key, added without a header of its own if it is missing
+fn section<'d>(doc: &'d mut DocumentMut, key: &str) -> anyhow::Result<&'d mut Table> {
+    doc.entry(key)
+        .or_insert_with(|| {
+            let mut table = Table::new();
+            table.set_implicit(true);
+            Item::Table(table)
+        })
+        .as_table_mut()
+        .ok_or_else(|| anyhow::anyhow!("'{}' in the config file is not a table", key))
+}
+
+/// Replace a string, keeping the whitespace and comments around it
+fn replace_str(value: &mut Value, new: &str) {
+    let decor = value.decor().clone();
+    *value = Value::from(new);
+    *value.decor_mut() = decor;
+}
+
+fn to_table(value: &impl Serialize) -> anyhow::Result<Table> {
+    Ok(toml_edit::ser::to_document(value)?.as_table().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, Pattern};
+
+    const CONFIG: &str = r#"# shared settings
+[tasks.install]
+command = "yarn install" # keep the lockfile in sync
+description = "Install dependencies"
+
+[tasks.build]
+depends_on = ["install"]
+command = "yarn build"
+"#;
+
+    #[test]
+    fn test_insert_keeps_the_rest_of_the_file() {
+        let mut document = ConfigDocument {
+            path: PathBuf::new(),
+            doc: CONFIG.parse().unwrap(),
+        };
+
+        let task = Config::load_from_string("[tasks.lint]\ncommand = \"yarn lint\"")
+            .unwrap()
+            .get_task("lint")
+            .unwrap()
+            .clone();
+        document.insert_task("lint", &task).unwrap();
+
+        assert!(!document.has_parser("eslint"));
+        document
+            .insert_parser(
+                "eslint",
+                &Parser {
+                    patterns: vec![Pattern {
+                        regex: r"(\d+) problems".to_string(),
+                        level: "error".to_string(),
+                        extract: Some("count".to_string()),
+                        action: None,
+                    }],
+                },
+            )
+            .unwrap();
+        assert!(document.has_parser("eslint"));
+
+        let written = document.doc.to_string();
+        assert!(written.starts_with(CONFIG));
+        assert_eq!(
+            &written[CONFIG.len()..],
+            r#"
+[tasks.lint]
+command = "yarn lint"
+
+[[parsers.eslint.patterns]]
+regex = '(\d+) problems'
+level = "error"
+extract = "count"
+"#
+        );
+        assert!(Config::load_from_string(&written).unwrap().has_task("lint"));
+    }
+
+    #[test]
+    fn test_update_and_rename_in_place() {
+        let content = r#"[tasks.install]
+command = "yarn install" # keep the lockfile in sync
+description = "Install dependencies"
+
+[tasks.build]
+depends_on = [ "install" ]
+command = "yarn build"
+
+[[parsers.deps.patterns]]
+regex = "lockfile changed"
+level = "warn"
+action = "run:install"
+"#;
+        let config = Config::load_from_string(content).unwrap();
+        let mut document = ConfigDocument {
+            path: PathBuf::new(),
+            doc: content.parse().unwrap(),
+        };
+
+        let old = config.get_task("install").unwrap();
+        let mut new = old.clone();
+        new.description = None;
+        new.retries = Some(2);
+
+        document.update_task("install", old, "deps", &new).unwrap();
+        document.rename_references("install", "deps");
+
+        assert_eq!(
+            document.doc.to_string(),
+            r#"[tasks.deps]
+command = "yarn install" # keep the lockfile in sync
+retries = 2
+
+[tasks.build]
+depends_on = [ "deps" ]
+command = "yarn build"
+
+[[parsers.deps.patterns]]
+regex = "lockfile changed"
+level = "warn"
+action = "run:deps"
+"#
+        );
+
+        assert!(document.remove_task("build").unwrap());
+        assert!(document.remove_task("deps").unwrap());
+        document.remove_parser("deps");
+        assert!(Config::load_from_string(&document.doc.to_string()).is_ok());
+    }
+}
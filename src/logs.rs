@@ -0,0 +1,255 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+const MANIFEST_FILE: &str = "manifest.toml";
+
+/// The logs of a single `taskr run`, stored in `<output_dir>/<run number>/`
+pub struct RunLog {
+    dir: PathBuf,
+    manifest: Mutex<RunManifest>,
+}
+
+/// Summary of a run, written next to the task logs
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RunManifest {
+    pub run: u32,
+    pub started_at: String,
+    pub targets: Vec<String>,
+    pub order: Vec<String>,
+    #[serde(default)]
+    pub tasks: Vec<TaskRecord>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TaskRecord {
+    pub name: String,
+    pub command: String,
+    pub started_at: String,
+    pub duration_secs: f64,
+    /// `None` when the process was killed by a signal or could not be started
+    pub exit_code: Option<i32>,
+    /// Whether the task was terminated for running longer than its `timeout`
+    #[serde(default)]
+    pub timed_out: bool,
+    /// Which attempt of the task this was, every attempt is recorded
+    #[serde(default = "first_attempt")]
+    pub attempt: u32,
+    /// Whether the cache found the task up to date, so it didn't run and has no exit code or
+    /// log file
+    #[serde(default)]
+    pub skipped: bool,
+    pub log_file: String,
+}
+
+/// Log file of one task, with both output streams interleaved
+pub struct TaskLog {
+    file: BufWriter<File>,
+}
+
+impl RunLog {
+    /// Create the directory for a new run, numbered one higher than the latest run
+    pub fn create(output_dir: &Path, targets: &[String], order: &[String]) -> anyhow::Result<Self> {
+        let run = list_runs(output_dir)?.last().map_or(1, |last| last + 1);
+        let dir = output_dir.join(run.to_string());
+
+        fs::create_dir_all(&dir)
+            .map_err(|e| anyhow::anyhow!("Failed to create log dir '{}': {}", dir.display(), e))?;
+
+        let log = Self {
+            dir,
+            manifest: Mutex::new(RunManifest {
+                run,
+                started_at: timestamp(),
+                targets: targets.to_vec(),
+                order: order.to_vec(),
+                tasks: Vec::new(),
+            }),
+        };
+        log.write_manifest(&log.manifest.lock().unwrap())?;
+
+        Ok(log)
+    }
+
+    pub fn task_log(&self, task_name: &str) -> anyhow::Result<TaskLog> {
+        let path = self.dir.join(log_file_name(task_name));
+        let file = File::create(&path).map_err(|e| {
+            anyhow::anyhow!("Failed to create log file '{}': {}", path.display(), e)
+        })?;
+
+        let mut log = TaskLog {
+            file: BufWriter::new(file),
+        };
+        let _ = writeln!(
+            log.file,
+            "# task '{}' started at {}",
+            task_name,
+            timestamp()
+        );
+
+        Ok(log)
+    }
+
+    /// Add a finished task to the manifest, which is rewritten so it stays current when a run
+    /// is interrupted
+    pub fn record(&self, record: TaskRecord) -> anyhow::Result<()> {
+        let mut manifest = self.manifest.lock().unwrap();
+        manifest.tasks.push(record);
+        self.write_manifest(&manifest)
+    }
+
+    fn write_manifest(&self, manifest: &RunManifest) -> anyhow::Result<()> {
+        fs::write(
+            self.dir.join(MANIFEST_FILE),
+            toml::to_string_pretty(manifest)?,
+        )?;
+        Ok(())
+    }
+}
+
+impl TaskLog {
+    /// Append a line, tagged with the time and the stream it was written to
+    pub fn write_line(&mut self, stream: &str, line: &str) {
+        let _ = writeln!(
+            self.file,
+            "[{}] [{}] {}",
+            Local::now().format("%H:%M:%S%.3f"),
+            stream,
+            line
+        );
+    }
+
+    /// Append a line that is not output of the task, e.g. where a retry starts
+    pub fn write_note(&mut self, note: &str) {
+        let _ = writeln!(self.file, "# {}", note);
+    }
+}
+
+impl Drop for TaskLog {
+    fn drop(&mut self) {
+        let _ = self.file.flush();
+    }
+}
+
+/// Numbers of all runs in the output dir, in ascending order
+pub fn list_runs(output_dir: &Path) -> anyhow::Result<Vec<u32>> {
+    if !output_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut runs: Vec<u32> = fs::read_dir(output_dir)?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .collect();
+    runs.sort();
+
+    Ok(runs)
+}
+
+pub fn read_manifest(output_dir: &Path, run: u32) -> anyhow::Result<RunManifest> {
+    let path = output_dir.join(run.to_string()).join(MANIFEST_FILE);
+    let content = fs::read_to_string(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read manifest '{}': {}", path.display(), e))?;
+
+    Ok(toml::from_str(&content)?)
+}
+
+fn first_attempt() -> u32 {
+    1
+}
+
+pub fn log_file_name(task_name: &str) -> String {
+    format!("{}.log", task_name.replace(['/', '\\'], "_"))
+}
+
+pub fn timestamp() -> String {
+    Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Fixtures shared by the tests of everything that writes to the output dir
+#[cfg(test)]
+pub mod test_util {
+    use super::*;
+
+    /// An empty directory of its own for a test
+    pub fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("taskr-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    pub fn record(name: &str, attempt: u32, exit_code: Option<i32>) -> TaskRecord {
+        TaskRecord {
+            name: name.to_string(),
+            command: format!("yarn {}", name),
+            started_at: timestamp(),
+            duration_secs: 1.5,
+            exit_code,
+            timed_out: false,
+            attempt,
+            skipped: false,
+            log_file: log_file_name(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_util::{record, temp_dir};
+    use super::*;
+
+    #[test]
+    fn test_runs_are_numbered_up() {
+        let output_dir = temp_dir("logs-runs");
+        assert!(list_runs(&output_dir).unwrap().is_empty());
+
+        let targets = vec!["build".to_string()];
+        RunLog::create(&output_dir, &targets, &targets).unwrap();
+        RunLog::create(&output_dir, &targets, &targets).unwrap();
+
+        // anything that isn't a run is skipped
+        fs::create_dir_all(output_dir.join("cache")).unwrap();
+        fs::write(output_dir.join("notes.txt"), "").unwrap();
+        assert_eq!(list_runs(&output_dir).unwrap(), vec![1, 2]);
+
+        let log = RunLog::create(&output_dir, &targets, &targets).unwrap();
+        assert_eq!(log.manifest.lock().unwrap().run, 3);
+
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn test_manifest_round_trips() {
+        let output_dir = temp_dir("logs-manifest");
+        let targets = vec!["test".to_string()];
+        let order = vec!["install".to_string(), "test".to_string()];
+
+        let log = RunLog::create(&output_dir, &targets, &order).unwrap();
+        log.record(record("install", 1, Some(0))).unwrap();
+        log.record(TaskRecord {
+            timed_out: true,
+            ..record("test", 2, None)
+        })
+        .unwrap();
+
+        let manifest = read_manifest(&output_dir, 1).unwrap();
+        assert_eq!(manifest.run, 1);
+        assert_eq!(manifest.targets, targets);
+        assert_eq!(manifest.order, order);
+        assert_eq!(manifest.tasks.len(), 2);
+
+        let test = &manifest.tasks[1];
+        assert_eq!(test.name, "test");
+        assert_eq!(test.exit_code, None);
+        assert!(test.timed_out);
+        assert_eq!(test.attempt, 2);
+        assert_eq!(test.log_file, "test.log");
+
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+}
@@ -1,36 +1,54 @@
+mod cache;
 mod cli;
 mod commands;
 mod config;
+mod dashboard;
+mod document;
+mod logs;
+mod matcher;
+mod output;
+mod runner;
+mod scheduler;
+mod shell;
+mod shutdown;
 mod watcher;
 
+use cache::Cache;
 use clap::Parser;
 use cli::{Cli, Commands};
-use config::{Config, Task};
+use config::Config;
+use logs::RunLog;
+use output::OutputOptions;
+use scheduler::Scheduler;
 use std::{
-    io::{BufRead, BufReader},
-    process::{self, Command, Stdio},
-    sync::mpsc,
-    thread,
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
 };
 
-#[derive(Debug)]
-enum OutputLine {
-    Stdout(String),
-    Stderr(String),
-}
-
 fn main() {
     let cli = Cli::parse();
 
+    // the only command that doesn't need a config file
+    if let Commands::Init(args) = &cli.command {
+        if let Err(err) = commands::init::init(args) {
+            eprintln!("{err}");
+            process::exit(1);
+        }
+        return;
+    }
+
     let config_path = match Config::find_config_file() {
         Some(path) => {
-            println!("Found config file: {}", path.display());
+            // on stderr, so machine-readable output on stdout stays parseable
+            eprintln!("Found config file: {}", path.display());
             path
         }
         None => {
             eprintln!(
-                "No config file found.. Looking for: taskr.toml, .tasks.toml, tasks.toml or task_runner.toml"
+                "No config file found.. Looking for: taskr.toml, .taskr.toml, tasks.toml or task_runner.toml"
             );
+            eprintln!("Create one with `taskr init`");
             process::exit(1);
         }
     };
@@ -44,238 +62,163 @@ fn main() {
     };
 
     match &cli.command {
-        Commands::Add(_) => {
-            if let Err(err) = commands::add::add_task() {
+        Commands::Init(_) => unreachable!("init is handled before the config is loaded"),
+        Commands::Add(args) => {
+            if let Err(err) = commands::add::add_task(&config, &config_path, args) {
                 eprintln!("{err}");
                 process::exit(1);
             }
         }
-        Commands::Run { name } => {
-            if let Err(err) = run_task_with_deps(&config, &name) {
+        Commands::Remove(args) => {
+            if let Err(err) = commands::remove::remove_task(&config, &config_path, args) {
                 eprintln!("{err}");
                 process::exit(1);
             }
         }
-        Commands::Summary => print_summary(&config),
-    }
-}
-
-fn run_task_with_deps(config: &Config, task_name: &str) -> anyhow::Result<()> {
-    // Check that the task exists
-    if !config.has_task(task_name) {
-        return Err(anyhow::anyhow!(
-            "Task '{}' not found in project configuration",
-            task_name
-        ));
-    }
-
-    let exec_order = config.get_exec_order(task_name)?;
-
-    println!(
-        "Executing commands in following order::: {}",
-        exec_order.join(" ==> ")
-    );
-
-    // For now we simulate the task runner, as I don't trust myself yet
-    for task in exec_order {
-        let task_config = config.get_task(&task).unwrap();
-
-        println!("🚀 Running task '{}'", task);
-
-        if let Some(desc) = &task_config.description {
-            println!("   📝 {}", desc);
+        Commands::Edit(args) => {
+            if let Err(err) = commands::edit::edit_task(&config, &config_path, args) {
+                eprintln!("{err}");
+                process::exit(1);
+            }
         }
-
-        println!("   💻 {}", task_config.command);
-        println!("   ─────────────────────────────────");
-
-        if let Err(e) = run_command(task_config) {
-            eprintln!("❌ Task '{}' failed: {}", task, e);
-
-            return Err(e);
+        Commands::Run {
+            names,
+            dependents,
+            keep_going,
+            force,
+            timestamps,
+            group,
+            tui,
+        } => {
+            let output_dir = output_dir(&config_path, &config);
+            let names = if names.is_empty() {
+                match commands::list::pick_task(&config) {
+                    Ok(name) => vec![name],
+                    Err(err) => {
+                        eprintln!("{err}");
+                        process::exit(1);
+                    }
+                }
+            } else {
+                names.clone()
+            };
+
+            let options = RunOptions {
+                dependents: *dependents,
+                keep_going: *keep_going,
+                force: *force,
+                tui: *tui,
+                output: OutputOptions {
+                    timestamps: *timestamps,
+                    group: *group,
+                    ..Default::default()
+                },
+            };
+
+            if let Err(err) = run_task_with_deps(&config, &output_dir, &names, &options) {
+                eprintln!("{err}");
+                process::exit(1);
+            }
         }
-
-        println!("✅ Task '{}' completed successfully", task);
-        println!();
-    }
-
-    Ok(())
-}
-
-fn run_command(task: &Task) -> anyhow::Result<()> {
-    let parts: Vec<&str> = task.command.split_whitespace().collect();
-    if parts.is_empty() {
-        return Err(anyhow::anyhow!("Empty command"));
-    }
-
-    let (cmd, args) = parts.split_first().unwrap();
-
-    let mut command = Command::new(cmd);
-    command.args(args);
-
-    if let Some(working_dir) = &task.working_dir {
-        command.current_dir(working_dir);
-    }
-
-    if let Some(env_vars) = &task.env {
-        for (key, value) in env_vars {
-            command.env(key, value);
+        Commands::Watch { name, debounce } => {
+            if let Err(err) = watcher::watch_task(&config, name, Duration::from_millis(*debounce)) {
+                eprintln!("{err}");
+                process::exit(1);
+            }
         }
-    }
-
-    command.stdout(Stdio::piped());
-    command.stderr(Stdio::piped());
-
-    let mut child = command
-        .spawn()
-        .map_err(|e| anyhow::anyhow!("Failed to start command '{}': {}", task.command, e))?;
-
-    let stdout = child
-        .stdout
-        .take()
-        .ok_or_else(|| anyhow::anyhow!("Failed to capture stdout"))?;
-    let stderr = child
-        .stderr
-        .take()
-        .ok_or_else(|| anyhow::anyhow!("Failed to capture stderr"))?;
-
-    let (tx, rx) = mpsc::channel();
-
-    let tx_stdout = tx.clone();
-    let stdout_handle = thread::spawn(move || {
-        let reader = BufReader::new(stdout);
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                let _ = tx_stdout.send(OutputLine::Stdout(line));
+        Commands::List(args) => {
+            if let Err(err) = commands::list::list_tasks(&config, args) {
+                eprintln!("{err}");
+                process::exit(1);
             }
         }
-    });
-
-    let tx_stderr = tx.clone();
-    let stderr_handle = thread::spawn(move || {
-        let reader = BufReader::new(stderr);
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                let _ = tx_stderr.send(OutputLine::Stderr(line));
+        Commands::Graph(args) => {
+            if let Err(err) = commands::graph::show_graph(&config, args) {
+                eprintln!("{err}");
+                process::exit(1);
             }
         }
-    });
-
-    drop(tx); // close sending end
-
-    for output in rx {
-        match output {
-            OutputLine::Stdout(line) => println!("        {}", line),
-            OutputLine::Stderr(line) => eprintln!("       {}", line),
+        Commands::Logs(args) => {
+            let output_dir = output_dir(&config_path, &config);
+            if let Err(err) = commands::logs::show_logs(&output_dir, args) {
+                eprintln!("{err}");
+                process::exit(1);
+            }
+        }
+        Commands::Summary(args) => {
+            if let Err(err) = commands::summary::show_summary(&config, args) {
+                eprintln!("{err}");
+                process::exit(1);
+            }
         }
     }
+}
 
-    let _ = stdout_handle.join();
-    let _ = stderr_handle.join();
-
-    let status = child
-        .wait()
-        .map_err(|e| anyhow::anyhow!("Failed to wait for process: {}", e))?;
+/// Flags of `taskr run` that change what is run and how
+struct RunOptions {
+    dependents: bool,
+    keep_going: bool,
+    force: bool,
+    tui: bool,
+    output: OutputOptions,
+}
 
-    if !status.success() {
-        return Err(anyhow::anyhow!(
-            "Command '{}' failed with exit code: {}",
-            task.command,
-            status.code().unwrap_or(-1)
-        ));
+fn run_task_with_deps(
+    config: &Config,
+    output_dir: &Path,
+    task_names: &[String],
+    options: &RunOptions,
+) -> anyhow::Result<()> {
+    let mut tasks = Vec::new();
+
+    for task_name in task_names {
+        // Check that the task exists
+        if !config.has_task(task_name) {
+            return Err(anyhow::anyhow!(
+                "Task '{}' not found in project configuration",
+                task_name
+            ));
+        }
+
+        if options.dependents {
+            tasks.extend(config.get_dependents_exec_order(task_name)?);
+        } else {
+            tasks.extend(config.get_exec_order(task_name)?);
+        }
     }
 
-    Ok(())
-}
-
-fn print_summary(config: &Config) {
-    let global = config.get_global_config();
+    // shared dependencies run once, before every target that needs them
+    let exec_order = config.order_tasks(&tasks)?;
 
-    println!("\n┌──────────────────────────────────────┐");
-    println!("│        🛠️  Task Runner Config         │");
-    println!("└──────────────────────────────────────┘");
     println!(
-        "  • Log Level     : {}",
-        global
-            .log_level
-            .clone()
-            .unwrap_or_else(|| "info".to_string())
-    );
-    println!("  • Max Parallel  : {}", global.max_parallel.unwrap_or(4));
-    println!(
-        "  • Output Dir    : {}",
-        global
-            .output_dir
-            .clone()
-            .unwrap_or_else(|| ".task-logs".to_string())
-    );
-
-    println!("\n┌──────────────────────────────────────┐");
-    println!(
-        "│          📋 Tasks ({})                 │",
-        config.tasks.len()
+        "Executing commands in following order::: {}",
+        exec_order.join(" ==> ")
     );
-    println!("└──────────────────────────────────────┘");
-
-    for (name, task) in &config.tasks {
-        println!("  • {}", name);
-        println!("     ├─ Command       : {}", task.command);
-
-        if let Some(desc) = &task.description {
-            println!("     ├─ Description   : {}", desc);
-        }
-
-        if let Some(deps) = &task.depends_on {
-            println!("     ├─ Dependencies  : {}", deps.join(", "));
-        }
 
-        if let Some(parsers) = &task.parsers {
-            println!("     ├─ Parsers       : {}", parsers.join(", "));
-        }
+    let run_log = RunLog::create(output_dir, task_names, &exec_order)?;
+    shutdown::install()?;
+    let cache = Cache::new(output_dir.join("cache"), options.force);
 
-        if let Some(watch) = &task.watch_files {
-            println!("     ├─ Watching      : {}", watch.join(", "));
-        }
+    let scheduler = Scheduler::new(config)
+        .with_run_log(&run_log)
+        .with_cache(&cache)
+        .with_keep_going(options.keep_going)
+        .with_output(options.output);
 
-        if task.auto_restart == Some(true) {
-            println!("     ├─ Auto-restart  : ✅ enabled");
-        }
-
-        if let Some(port) = task.port_check {
-            println!("     └─ Port check    : {}", port);
-        } else {
-            println!("     └─ Port check    : (none)");
-        }
-
-        println!();
+    if options.tui {
+        dashboard::run(&scheduler, &exec_order)
+    } else {
+        scheduler.run(&exec_order)
     }
+}
 
-    if let Some(parsers) = &config.parsers {
-        println!("┌──────────────────────────────────────┐");
-        println!("│          🔍 Parsers ({})               │", parsers.len());
-        println!("└──────────────────────────────────────┘");
+/// The output dir is relative to the config file, so logs end up in the same place no matter
+/// which subdirectory taskr is run from
+fn output_dir(config_path: &Path, config: &Config) -> PathBuf {
+    let output_dir = config.get_global_settings().output_dir;
 
-        for (name, parser) in parsers {
-            println!("  • {}", name);
-            println!("     ├─ Pattern count : {}", parser.patterns.len());
-            for pattern in &parser.patterns {
-                println!(
-                    "     └─ Pattern       : {}  [{}]",
-                    pattern.regex, pattern.level
-                );
-            }
-            println!();
-        }
-    }
-
-    let root_tasks = config.get_root_tasks();
-    if !root_tasks.is_empty() {
-        println!("┌──────────────────────────────────────┐");
-        println!("│       🌱 Root Tasks (no deps)         │");
-        println!("└──────────────────────────────────────┘");
-        for task in root_tasks {
-            println!("  • {}", task);
-        }
-        println!();
-    }
+    config_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(output_dir)
 }
@@ -0,0 +1,424 @@
+use crate::config::{Config, Task};
+use console::Style;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The parser patterns of a single task, compiled so they can be matched against every line
+/// of its output
+pub struct OutputMatcher {
+    patterns: Vec<CompiledPattern>,
+}
+
+struct CompiledPattern {
+    parser: String,
+    regex: Regex,
+    level: String,
+    extract: Option<String>,
+    action: Option<Action>,
+}
+
+/// A line of output that matched one of the task's parser patterns
+#[derive(Debug)]
+pub struct LineMatch<'a> {
+    pub parser: &'a str,
+    pub level: &'a str,
+    /// The `extract` name together with the value it captured
+    pub extracted: Option<(&'a str, String)>,
+    pub action: Option<&'a Action>,
+}
+
+/// What happens when a pattern matches, set with `action` on the pattern
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Stop the task and mark it as failed
+    Fail,
+    /// Stop the task and start it again
+    Restart,
+    /// Call attention to the line
+    Notify,
+    /// Run another task, with the line and the extracted value in its environment
+    Run(String),
+    /// Hide the line from the terminal and the roll-up
+    Ignore,
+}
+
+/// An action of a pattern that matched, together with what it matched
+#[derive(Debug, Clone)]
+pub struct FiredAction {
+    pub action: Action,
+    pub line: String,
+    pub extracted: Option<(String, String)>,
+}
+
+/// Picks out matches by level, optionally limited to one parser: `success` or `nextjs:success`
+#[derive(Debug, PartialEq)]
+pub struct MatchSelector {
+    pub parser: Option<String>,
+    pub level: String,
+}
+
+/// Per-task roll-up of everything the parsers matched
+#[derive(Debug, Default)]
+pub struct MatchSummary {
+    pub counts: BTreeMap<String, usize>,
+    pub extracted: BTreeMap<String, String>,
+    /// Every parser and level that matched at least once
+    pub matched: BTreeSet<(String, String)>,
+}
+
+impl OutputMatcher {
+    /// Compile the patterns of all parsers the task uses, in the order they are listed
+    pub fn for_task(config: &Config, task: &Task) -> anyhow::Result<Self> {
+        let mut patterns = Vec::new();
+
+        for parser_name in task.parsers.iter().flatten() {
+            let parser = config
+                .parsers
+                .as_ref()
+                .and_then(|parsers| parsers.get(parser_name))
+                .ok_or_else(|| anyhow::anyhow!("Unknown parser '{}'", parser_name))?;
+
+            for pattern in &parser.patterns {
+                patterns.push(CompiledPattern {
+                    parser: parser_name.clone(),
+                    regex: Regex::new(&pattern.regex)?,
+                    level: pattern.level.clone(),
+                    extract: pattern.extract.clone(),
+                    action: pattern
+                        .action
+                        .as_deref()
+                        .map(|action| {
+                            Action::parse(action)
+                                .ok_or_else(|| anyhow::anyhow!("Unknown action '{}'", action))
+                        })
+                        .transpose()?,
+                });
+            }
+        }
+
+        Ok(Self { patterns })
+    }
+
+    /// Match a line against the patterns, the first pattern that matches wins
+    pub fn match_line(&self, line: &str) -> Option<LineMatch<'_>> {
+        self.patterns.iter().find_map(|pattern| {
+            let captures = pattern.regex.captures(line)?;
+
+            let extracted = pattern.extract.as_deref().map(|name| {
+                // bind the first capture group, or the whole match if the regex has none
+                let value = captures.get(1).or_else(|| captures.get(0)).unwrap();
+                (name, value.as_str().to_string())
+            });
+
+            Some(LineMatch {
+                parser: &pattern.parser,
+                level: &pattern.level,
+                extracted,
+                action: pattern.action.as_ref(),
+            })
+        })
+    }
+}
+
+impl Action {
+    /// Parse one of `fail`, `restart`, `notify`, `ignore` or `run:<task>`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "fail" => Some(Action::Fail),
+            "restart" => Some(Action::Restart),
+            "notify" => Some(Action::Notify),
+            "ignore" => Some(Action::Ignore),
+            other => other
+                .strip_prefix("run:")
+                .map(str::trim)
+                .filter(|task| !task.is_empty())
+                .map(|task| Action::Run(task.to_string())),
+        }
+    }
+}
+
+impl FiredAction {
+    pub fn new(action: &Action, line: &str, line_match: &LineMatch) -> Self {
+        Self {
+            action: action.clone(),
+            line: line.to_string(),
+            extracted: line_match
+                .extracted
+                .as_ref()
+                .map(|(name, value)| (name.to_string(), value.clone())),
+        }
+    }
+
+    /// Environment handed to a `run:<task>` action: `TASKR_MATCH_LINE` with the whole line, and
+    /// `TASKR_MATCH_<NAME>` with the extracted value, e.g. `TASKR_MATCH_MODULE` for
+    /// `extract = "module"`
+    pub fn env(&self) -> Vec<(String, String)> {
+        let mut env = vec![("TASKR_MATCH_LINE".to_string(), self.line.clone())];
+
+        if let Some((name, value)) = &self.extracted {
+            let name: String = name
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() {
+                        c.to_ascii_uppercase()
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            env.push((format!("TASKR_MATCH_{}", name), value.clone()));
+        }
+
+        env
+    }
+}
+
+/// Describe what a fired action matched, e.g. "Module not found (module=./foo)"
+impl std::fmt::Display for FiredAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.extracted {
+            Some((name, value)) => write!(f, "{} ({}={})", self.line, name, value),
+            None => write!(f, "{}", self.line),
+        }
+    }
+}
+
+impl MatchSelector {
+    pub fn parse(selector: &str) -> Self {
+        match selector.split_once(':') {
+            Some((parser, level)) => Self {
+                parser: Some(parser.trim().to_string()),
+                level: level.trim().to_string(),
+            },
+            None => Self {
+                parser: None,
+                level: selector.trim().to_string(),
+            },
+        }
+    }
+
+    pub fn matches(&self, line_match: &LineMatch) -> bool {
+        self.accepts(line_match.parser, line_match.level)
+    }
+
+    /// Whether a pattern with this parser and level would be picked out
+    pub fn accepts(&self, parser: &str, level: &str) -> bool {
+        self.level == level && self.parser.as_deref().is_none_or(|p| p == parser)
+    }
+}
+
+impl MatchSummary {
+    pub fn record(&mut self, line_match: &LineMatch) {
+        *self.counts.entry(line_match.level.to_string()).or_default() += 1;
+        self.matched
+            .insert((line_match.parser.to_string(), line_match.level.to_string()));
+
+        if let Some((name, value)) = &line_match.extracted {
+            self.extracted.insert(name.to_string(), value.clone());
+        }
+    }
+
+    pub fn count(&self, level: &str) -> usize {
+        self.counts.get(level).copied().unwrap_or(0)
+    }
+
+    /// Whether any line matched the selector
+    pub fn has_match(&self, selector: &MatchSelector) -> bool {
+        self.matched
+            .iter()
+            .any(|(parser, level)| selector.accepts(parser, level))
+    }
+
+    /// Whether there is nothing to summarise: no warnings, no errors and no extracted values.
+    /// Lines matching other levels only count towards `has_match`
+    pub fn is_empty(&self) -> bool {
+        self.count("warn") == 0 && self.count("error") == 0 && self.extracted.is_empty()
+    }
+}
+
+/// Summarise the warnings, errors and extracted values, e.g. "3 warnings, 1 error, duration=12.3"
+impl std::fmt::Display for MatchSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+
+        for (level, noun) in [("warn", "warning"), ("error", "error")] {
+            match self.count(level) {
+                0 => {}
+                1 => parts.push(format!("1 {}", noun)),
+                n => parts.push(format!("{} {}s", n, noun)),
+            }
+        }
+
+        for (name, value) in &self.extracted {
+            parts.push(format!("{}={}", name, value));
+        }
+
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// Color used to render lines of the given level
+pub fn level_style(level: &str) -> Style {
+    match level {
+        "error" => Style::new().red(),
+        "warn" => Style::new().yellow(),
+        "success" => Style::new().green(),
+        "info" => Style::new().cyan(),
+        _ => Style::new(),
+    }
+}
+
+/// Render a matched line with a colored level tag in front of it
+pub fn format_match(line: &str, line_match: &LineMatch) -> String {
+    let style = level_style(line_match.level);
+    format!(
+        "{} {}",
+        style.apply_to(format!("[{}]", line_match.level)).bold(),
+        style.apply_to(line)
+    )
+}
+
+/// Render the roll-up printed once a task has finished
+pub fn format_summary(summary: &MatchSummary) -> String {
+    let style = if summary.count("error") > 0 {
+        level_style("error")
+    } else if summary.count("warn") > 0 {
+        level_style("warn")
+    } else {
+        level_style("success")
+    };
+
+    format!("   📊 {}", style.apply_to(summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher() -> OutputMatcher {
+        let config = Config::load_from_string(
+            r#"
+[tasks.install]
+command = "yarn install"
+parsers = ["yarn-install"]
+
+[parsers.yarn-install]
+patterns = [
+    { regex = "warning (.+)", level = "warn", extract = "message" },
+    { regex = "error (.+)", level = "error", extract = "message" },
+    { regex = "Done in (.+)s", level = "success", extract = "duration" }
+]
+            "#,
+        )
+        .unwrap();
+
+        OutputMatcher::for_task(&config, config.get_task("install").unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_match_line_extracts_capture_group() {
+        let matcher = matcher();
+
+        let line_match = matcher
+            .match_line("warning package.json: No license field")
+            .unwrap();
+        assert_eq!(line_match.parser, "yarn-install");
+        assert_eq!(line_match.level, "warn");
+        assert_eq!(
+            line_match.extracted,
+            Some(("message", "package.json: No license field".to_string()))
+        );
+
+        assert!(matcher.match_line("[1/4] Resolving packages...").is_none());
+    }
+
+    #[test]
+    fn test_selector_matches_level_and_parser() {
+        let matcher = matcher();
+        let line_match = matcher.match_line("✨  Done in 3.1s").unwrap();
+
+        assert!(MatchSelector::parse("success").matches(&line_match));
+        assert!(MatchSelector::parse("yarn-install:success").matches(&line_match));
+        assert!(!MatchSelector::parse("nextjs:success").matches(&line_match));
+        assert!(!MatchSelector::parse("error").matches(&line_match));
+    }
+
+    #[test]
+    fn test_parse_action() {
+        assert_eq!(Action::parse("fail"), Some(Action::Fail));
+        assert_eq!(
+            Action::parse("run: install"),
+            Some(Action::Run("install".to_string()))
+        );
+        assert_eq!(Action::parse("run:"), None);
+        assert_eq!(Action::parse("explode"), None);
+    }
+
+    #[test]
+    fn test_fired_action_env() {
+        let line_match = LineMatch {
+            parser: "webpack",
+            level: "error",
+            extracted: Some(("missing-module", "./Button".to_string())),
+            action: None,
+        };
+        let fired = FiredAction::new(
+            &Action::Run("install".to_string()),
+            "Module not found: ./Button",
+            &line_match,
+        );
+
+        assert_eq!(
+            fired.env(),
+            vec![
+                (
+                    "TASKR_MATCH_LINE".to_string(),
+                    "Module not found: ./Button".to_string()
+                ),
+                (
+                    "TASKR_MATCH_MISSING_MODULE".to_string(),
+                    "./Button".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summary_rolls_up_matches() {
+        let matcher = matcher();
+        let mut summary = MatchSummary::default();
+
+        for line in [
+            "warning one",
+            "warning two",
+            "warning three",
+            "error something broke",
+            "✨  Done in 12.3s",
+        ] {
+            summary.record(&matcher.match_line(line).unwrap());
+        }
+
+        assert_eq!(summary.count("warn"), 3);
+        assert!(summary.has_match(&MatchSelector::parse("yarn-install:error")));
+        assert!(!summary.has_match(&MatchSelector::parse("info")));
+        assert_eq!(
+            summary.to_string(),
+            "3 warnings, 1 error, duration=12.3, message=something broke"
+        );
+    }
+
+    #[test]
+    fn test_summary_without_warnings_or_values_is_empty() {
+        let mut summary = MatchSummary::default();
+        summary.record(&LineMatch {
+            parser: "server",
+            level: "success",
+            extracted: None,
+            action: None,
+        });
+
+        assert!(summary.has_match(&MatchSelector::parse("server:success")));
+        assert!(summary.is_empty());
+        assert_eq!(summary.to_string(), "");
+    }
+}
@@ -0,0 +1,234 @@
+use chrono::Local;
+use console::Style;
+use std::{
+    fmt::Display,
+    io::{self, Write},
+    sync::{Mutex, MutexGuard, PoisonError, mpsc::Sender},
+};
+
+/// Held while lines are written, so a group of lines from one task stays together
+static TERMINAL: Mutex<()> = Mutex::new(());
+
+/// Receives everything printed about tasks instead of the terminal, while the dashboard is open
+static CAPTURE: Mutex<Option<Sender<Captured>>> = Mutex::new(None);
+
+/// Colors task names are picked from: cyan, magenta, blue, green, yellow and orange
+const PALETTE: [u8; 6] = [6, 5, 4, 2, 3, 208];
+
+/// How the output of tasks is written to the terminal
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OutputOptions {
+    /// Width task names are padded to, so the output of all tasks lines up
+    pub width: usize,
+    /// Start every line with the time it was printed
+    pub timestamps: bool,
+    /// Hold back the output of a task until it finishes, then print it in one go
+    pub group: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// A line printed about a task while output is captured
+#[derive(Debug)]
+pub enum Captured {
+    /// A line of the task's output, with the level of the parser pattern it matched
+    Output {
+        task: String,
+        stream: Stream,
+        line: String,
+        level: Option<String>,
+    },
+    /// What taskr reports about the task, like it starting or failing
+    Status {
+        task: String,
+        stream: Stream,
+        line: String,
+    },
+}
+
+/// Send everything printed about tasks to `tx` instead of the terminal, until `release`
+pub fn capture(tx: Sender<Captured>) {
+    *lock(&CAPTURE) = Some(tx);
+}
+
+pub fn release() {
+    lock(&CAPTURE).take();
+}
+
+/// Print what taskr reports about a task to stdout, or hand it to the dashboard
+pub fn status(task: &str, line: impl Display) {
+    report(task, Stream::Stdout, line);
+}
+
+/// Print what taskr reports about a task to stderr, or hand it to the dashboard
+pub fn status_err(task: &str, line: impl Display) {
+    report(task, Stream::Stderr, line);
+}
+
+fn report(task: &str, stream: Stream, line: impl Display) {
+    let line = line.to_string();
+
+    if let Some(tx) = lock(&CAPTURE).as_ref() {
+        let _ = tx.send(Captured::Status {
+            task: task.to_string(),
+            stream,
+            line,
+        });
+        return;
+    }
+
+    let _terminal = lock(&TERMINAL);
+    write(stream, &line);
+}
+
+/// Writes the output of one task, every line prefixed with the task name in its own color
+pub struct TaskOutput {
+    name: String,
+    prefix: String,
+    timestamps: bool,
+    group: bool,
+    buffer: Vec<(Stream, String)>,
+}
+
+impl TaskOutput {
+    pub fn new(name: &str, options: &OutputOptions) -> Self {
+        let style = Style::new().color256(task_color(name)).bold();
+
+        Self {
+            name: name.to_string(),
+            prefix: style
+                .apply_to(format!("{:<width$}", name, width = options.width))
+                .to_string(),
+            timestamps: options.timestamps,
+            group: options.group,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Print a line of output, `level` being the level of the parser pattern it matched
+    pub fn line(&mut self, stream: Stream, content: &str, level: Option<&str>) {
+        if let Some(tx) = lock(&CAPTURE).as_ref() {
+            let _ = tx.send(Captured::Output {
+                task: self.name.clone(),
+                stream,
+                line: content.to_string(),
+                level: level.map(str::to_string),
+            });
+            return;
+        }
+
+        let line = self.format(stream, content);
+
+        if self.group {
+            self.buffer.push((stream, line));
+        } else {
+            let _terminal = lock(&TERMINAL);
+            write(stream, &line);
+        }
+    }
+
+    /// Print everything held back in `--group` mode
+    pub fn flush(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let _terminal = lock(&TERMINAL);
+        for (stream, line) in self.buffer.drain(..) {
+            write(stream, &line);
+        }
+    }
+
+    /// `[12:00:01.123] web    │ line`, with `!` instead of `│` for stderr
+    fn format(&self, stream: Stream, content: &str) -> String {
+        let separator = match stream {
+            Stream::Stdout => Style::new().dim().apply_to("│"),
+            Stream::Stderr => Style::new().red().bold().apply_to("!"),
+        };
+
+        let mut line = String::new();
+        if self.timestamps {
+            let time = Local::now().format("%H:%M:%S%.3f").to_string();
+            line.push_str(&format!("{} ", Style::new().dim().apply_to(time)));
+        }
+        line.push_str(&format!("{} {} {}", self.prefix, separator, content));
+
+        line
+    }
+}
+
+impl Drop for TaskOutput {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// The same task always gets the same color
+fn task_color(name: &str) -> u8 {
+    let hash = name.bytes().fold(0usize, |hash, b| {
+        hash.wrapping_mul(31).wrapping_add(b as usize)
+    });
+
+    PALETTE[hash % PALETTE.len()]
+}
+
+/// Write a line to the terminal. Write errors are ignored, so output piped to a reader that
+/// went away, like `taskr run build | head`, doesn't bring down the task writing it
+fn write(stream: Stream, line: &str) {
+    let _ = match stream {
+        Stream::Stdout => writeln!(io::stdout().lock(), "{}", line),
+        Stream::Stderr => writeln!(io::stderr().lock(), "{}", line),
+    };
+}
+
+/// Lock a mutex even if a thread panicked while holding it, the data behind these stays valid
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_pads_name_and_marks_stderr() {
+        let output = TaskOutput::new(
+            "web",
+            &OutputOptions {
+                width: 7,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            console::strip_ansi_codes(&output.format(Stream::Stdout, "compiled")),
+            "web     │ compiled"
+        );
+        assert_eq!(
+            console::strip_ansi_codes(&output.format(Stream::Stderr, "oops")),
+            "web     ! oops"
+        );
+    }
+
+    #[test]
+    fn test_group_holds_back_lines() {
+        let mut output = TaskOutput::new(
+            "api",
+            &OutputOptions {
+                group: true,
+                ..Default::default()
+            },
+        );
+
+        output.line(Stream::Stdout, "one", None);
+        output.line(Stream::Stderr, "two", Some("error"));
+        assert_eq!(output.buffer.len(), 2);
+
+        output.flush();
+        assert!(output.buffer.is_empty());
+    }
+}
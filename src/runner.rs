@@ -0,0 +1,933 @@
+use crate::cache::Cache;
+use crate::config::{Config, Task};
+use crate::logs::{self, RunLog, TaskLog, TaskRecord};
+use crate::matcher::{self, Action, FiredAction, MatchSelector, MatchSummary, OutputMatcher};
+use crate::output::{self, OutputOptions, Stream, TaskOutput};
+use crate::scheduler::TaskRunner;
+use crate::shell::Shell;
+use crate::shutdown::{self, Interrupted};
+use std::{
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    io::{BufRead, BufReader, ErrorKind, Read},
+    net::{TcpStream, ToSocketAddrs},
+    os::unix::process::CommandExt,
+    process::{Child, Stdio},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread::{self, JoinHandle, Scope, ScopedJoinHandle},
+    time::{Duration, Instant},
+};
+
+/// Seconds a task gets to become ready before it is considered failed
+const DEFAULT_READY_TIMEOUT: u64 = 60;
+
+/// Milliseconds between attempts to connect to the `port_check` port
+const DEFAULT_PORT_POLL_INTERVAL: u64 = 250;
+
+/// How many times a `restart` pattern may restart a process before the task fails, so a pattern
+/// that matches during startup doesn't restart it forever
+pub const MAX_RESTARTS: u32 = 5;
+
+/// How often a running task is checked for readiness, exit and stop requests
+const SUPERVISE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A task that ran longer than its `timeout` and was terminated
+#[derive(Debug, thiserror::Error)]
+#[error("Timed out after {0}s")]
+pub struct TimedOut(pub u64);
+
+/// A task that was asked to stop before it became ready, so its dependents must not run
+#[derive(Debug, thiserror::Error)]
+#[error("Stopped before becoming ready")]
+pub struct Stopped;
+
+/// Everything a task run needs besides the task itself
+pub struct RunContext<'a> {
+    pub config: &'a Config,
+    /// Where task output and the run manifest are written, if anywhere
+    pub run_log: Option<&'a RunLog>,
+    /// Skips tasks whose inputs are unchanged since they last succeeded, if set
+    pub cache: Option<&'a Cache>,
+    pub output: OutputOptions,
+    pub stops: &'a StopRequests,
+}
+
+impl TaskRunner for RunContext<'_> {
+    fn run(&self, name: &str, task: &Task, on_ready: &dyn Fn()) -> anyhow::Result<()> {
+        run_task(self, name, task, on_ready)
+    }
+
+    fn stop(&self, name: &str) {
+        self.stops.request(name);
+    }
+
+    fn interrupted(&self) -> bool {
+        shutdown::requested()
+    }
+}
+
+/// Requests from the scheduler to stop running tasks, picked up by the threads supervising the
+/// task processes
+#[derive(Default)]
+pub struct StopRequests {
+    names: Mutex<HashSet<String>>,
+}
+
+impl StopRequests {
+    pub fn request(&self, name: &str) {
+        self.names.lock().unwrap().insert(name.to_string());
+    }
+
+    /// Forget an earlier request, so the task can run again
+    pub fn withdraw(&self, name: &str) {
+        self.names.lock().unwrap().remove(name);
+    }
+
+    /// Whether the task should stop, either on its own or because taskr is shutting down
+    pub fn is_requested(&self, name: &str) -> bool {
+        shutdown::requested() || self.names.lock().unwrap().contains(name)
+    }
+}
+
+/// Run a single task, printing a header before and the outcome after. `on_ready` is called
+/// once the task passes its readiness check, for tasks that have one
+pub fn run_task(
+    ctx: &RunContext,
+    name: &str,
+    task: &Task,
+    on_ready: &dyn Fn(),
+) -> anyhow::Result<()> {
+    let cache_key = ctx.cache.and_then(|cache| {
+        cache
+            .key(ctx.config, name)
+            .map(|key| key.map(|key| (cache, key)))
+            .unwrap_or_else(|e| {
+                output::status_err(name, format!("⚠️  Not caching task '{}': {}", name, e));
+                None
+            })
+    });
+
+    if let Some((cache, key)) = &cache_key
+        && cache.is_fresh(ctx.config, name, task, key)
+    {
+        output::status(
+            name,
+            format!(
+                "⏭️  Task '{}' is up to date, skipping (use --force to run it)",
+                name
+            ),
+        );
+        output::status(name, "");
+
+        return match ctx.run_log {
+            Some(run_log) => run_log.record(TaskRecord {
+                name: name.to_string(),
+                command: task.command.clone(),
+                started_at: logs::timestamp(),
+                duration_secs: 0.0,
+                exit_code: None,
+                timed_out: false,
+                attempt: 1,
+                skipped: true,
+                log_file: String::new(),
+            }),
+            None => Ok(()),
+        };
+    }
+
+    output::status(name, format!("🚀 Running task '{}'", name));
+
+    if let Some(desc) = &task.description {
+        output::status(name, format!("   📝 {}", desc));
+    }
+
+    output::status(name, format!("   💻 {}", task.command));
+    output::status(name, "   ─────────────────────────────────");
+
+    let (result, attempts) = run_attempts(ctx, name, task, on_ready);
+    let after = if attempts > 1 {
+        format!(" after {} attempts", attempts)
+    } else {
+        String::new()
+    };
+
+    if let Err(e) = result {
+        if e.is::<Interrupted>() || e.is::<Stopped>() {
+            output::status(name, format!("⏹️  Stopped task '{}'", name));
+        } else if e.is::<TimedOut>() {
+            output::status_err(
+                name,
+                format!("⏰ Task '{}' timed out{}: {}", name, after, e),
+            );
+        } else {
+            output::status_err(name, format!("❌ Task '{}' failed{}: {}", name, after, e));
+        }
+
+        return Err(e);
+    }
+
+    if ctx.stops.is_requested(name) {
+        output::status(name, format!("⏹️  Stopped task '{}'", name));
+    } else {
+        output::status(
+            name,
+            format!("✅ Task '{}' completed successfully{}", name, after),
+        );
+
+        if let Some((cache, key)) = &cache_key
+            && let Err(e) = cache.store(name, key)
+        {
+            output::status_err(name, format!("⚠️  Failed to cache task '{}': {}", name, e));
+        }
+    }
+    output::status(name, "");
+
+    Ok(())
+}
+
+/// Run the task's command until it succeeds or has no retries left. Returns the outcome of the
+/// last attempt and the number of attempts
+fn run_attempts(
+    ctx: &RunContext,
+    name: &str,
+    task: &Task,
+    on_ready: &dyn Fn(),
+) -> (anyhow::Result<()>, u32) {
+    // all attempts write to the same log file
+    let mut task_log = match ctx.run_log.map(|log| log.task_log(name)).transpose() {
+        Ok(task_log) => task_log,
+        Err(e) => return (Err(e), 1),
+    };
+
+    let max_attempts = task.retries.unwrap_or(0) + 1;
+    let mut attempt = 1;
+
+    loop {
+        let (result, retryable) = run_command(ctx, name, task, on_ready, attempt, &mut task_log);
+
+        let Err(e) = &result else {
+            return (result, attempt);
+        };
+        if !retryable || attempt >= max_attempts {
+            return (result, attempt);
+        }
+
+        let delay = retry_delay(task, attempt);
+        output::status_err(
+            name,
+            format!(
+                "🔁 Task '{}' failed on attempt {} of {}, retrying in {:.1}s: {}",
+                name,
+                attempt,
+                max_attempts,
+                delay.as_secs_f64(),
+                e
+            ),
+        );
+
+        let deadline = Instant::now() + delay;
+        while Instant::now() < deadline {
+            if ctx.stops.is_requested(name) {
+                return (result, attempt);
+            }
+            thread::sleep(SUPERVISE_INTERVAL);
+        }
+
+        attempt += 1;
+        output::status(
+            name,
+            format!(
+                "🔁 Running task '{}' again (attempt {} of {})",
+                name, attempt, max_attempts
+            ),
+        );
+    }
+}
+
+/// Delay before the attempt after `attempt`, doubled after every attempt with `retry_backoff`
+fn retry_delay(task: &Task, attempt: u32) -> Duration {
+    let delay = Duration::from_secs(task.retry_delay.unwrap_or(0));
+
+    if task.retry_backoff == Some(true) {
+        delay.saturating_mul(2u32.saturating_pow(attempt - 1))
+    } else {
+        delay
+    }
+}
+
+/// Run the task's command once. Returns the outcome and whether a failure may be retried,
+/// which it may when the task was not stopped, never became ready, and matched one of its
+/// `retry_on` selectors if it has any
+pub fn run_command(
+    ctx: &RunContext,
+    name: &str,
+    task: &Task,
+    on_ready: &dyn Fn(),
+    attempt: u32,
+    task_log: &mut Option<TaskLog>,
+) -> (anyhow::Result<()>, bool) {
+    if let Some(port) = task.port_check
+        && port_is_open(port)
+    {
+        return (
+            Err(anyhow::anyhow!(
+                "Port {} is already in use before starting the task, is it already running?",
+                port
+            )),
+            false,
+        );
+    }
+
+    if attempt > 1
+        && let Some(task_log) = task_log.as_mut()
+    {
+        task_log.write_note(&format!(
+            "attempt {} started at {}",
+            attempt,
+            logs::timestamp()
+        ));
+    }
+
+    let started_at = logs::timestamp();
+    let start = Instant::now();
+
+    // dependents were released once the task became ready, so it can't be retried after that
+    let became_ready = Cell::new(false);
+    let on_ready = || {
+        became_ready.set(true);
+        on_ready();
+    };
+    let mut matched_retry_on = false;
+
+    let spawned = RunningProcess::spawn(ctx.config, name, task, &ctx.output, task_log.take());
+    let (result, exit_code) = match spawned {
+        Ok(mut process) => {
+            let ready =
+                thread::scope(|scope| supervise(scope, &mut process, ctx, name, task, &on_ready));
+            let exited = process.wait();
+            process.print_summary();
+
+            let mut result = match ready {
+                _ if shutdown::requested() => Err(Interrupted.into()),
+                // only a service that became ready released its dependents already
+                _ if ctx.stops.is_requested(name) && became_ready.get() => Ok(()),
+                _ if ctx.stops.is_requested(name) => Err(Stopped.into()),
+                Ok(true) => exited,
+                Ok(false) => {
+                    exited.and_then(|()| Err(anyhow::anyhow!("Exited before becoming ready")))
+                }
+                Err(e) => Err(e),
+            };
+
+            // lines printed right before the process exited are only handled now that all of
+            // its output has been read
+            for fired in process.fired_actions() {
+                match &fired.action {
+                    Action::Fail if result.is_ok() && !ctx.stops.is_requested(name) => {
+                        result = Err(fail_error(&fired));
+                    }
+                    Action::Run(target) => run_hook(ctx.config, target, &fired),
+                    Action::Notify => notify(name, &fired),
+                    _ => {}
+                }
+            }
+
+            matched_retry_on = task.retry_on.as_ref().is_none_or(|selectors| {
+                selectors
+                    .iter()
+                    .any(|selector| process.summary().has_match(&MatchSelector::parse(selector)))
+            });
+            *task_log = process.take_task_log();
+
+            (result, process.exit_code)
+        }
+        Err(e) => (Err(e), None),
+    };
+
+    if let Some(run_log) = ctx.run_log
+        && let Err(e) = run_log.record(TaskRecord {
+            name: name.to_string(),
+            command: task.command.clone(),
+            started_at,
+            duration_secs: start.elapsed().as_secs_f64(),
+            exit_code,
+            timed_out: result.as_ref().is_err_and(|e| e.is::<TimedOut>()),
+            attempt,
+            skipped: false,
+            log_file: logs::log_file_name(name),
+        })
+    {
+        return (Err(e), false);
+    }
+
+    let retryable = !ctx.stops.is_requested(name) && !became_ready.get() && matched_retry_on;
+    (result, retryable)
+}
+
+/// Watch over a running task until its process exits: call `on_ready` once the task passes its
+/// readiness checks, carry out the actions of patterns its output matches, and terminate the
+/// process when the scheduler asks to stop it. A task with a `port_check` is ready once the port
+/// accepts connections, a task with `ready_on` once a line of its output matches, and a task
+/// with both once both happened. Returns whether the task became ready, and fails when it is
+/// still not ready once `ready_timeout` has passed, a `fail` pattern matched, or it ran longer
+/// than its `timeout`. Tasks started by `run:<task>` actions run on `scope`, so they are waited
+/// for before the task finishes
+fn supervise<'scope, 'env>(
+    scope: &'scope Scope<'scope, 'env>,
+    process: &mut RunningProcess,
+    ctx: &'env RunContext,
+    name: &str,
+    task: &Task,
+    on_ready: &dyn Fn(),
+) -> anyhow::Result<bool> {
+    let mut hooks: HashMap<String, ScopedJoinHandle<'scope, ()>> = HashMap::new();
+
+    let mut port_ready = task.port_check.is_none();
+    let mut output_ready = task.ready_on.is_none();
+    let mut ready = port_ready && output_ready;
+
+    let timeout = Duration::from_secs(task.ready_timeout.unwrap_or(DEFAULT_READY_TIMEOUT));
+    let interval = Duration::from_millis(
+        task.port_poll_interval
+            .unwrap_or(DEFAULT_PORT_POLL_INTERVAL),
+    );
+    let deadline = Instant::now() + timeout;
+    let mut next_probe = Instant::now();
+
+    let settings = ctx.config.get_task_settings(task);
+    let grace = Duration::from_secs(settings.kill_grace);
+    let run_timeout = settings.timeout;
+    let run_deadline = run_timeout.map(|secs| Instant::now() + Duration::from_secs(secs));
+
+    loop {
+        if ctx.stops.is_requested(name) {
+            process.terminate(grace);
+            return Ok(ready);
+        }
+
+        if let Some(run_deadline) = run_deadline
+            && Instant::now() >= run_deadline
+            && !process.has_exited()
+        {
+            process.terminate(grace);
+            return Err(TimedOut(run_timeout.unwrap_or_default()).into());
+        }
+
+        for fired in process.fired_actions() {
+            match &fired.action {
+                Action::Fail => {
+                    process.terminate(grace);
+                    return Err(fail_error(&fired));
+                }
+                Action::Restart => {
+                    output::status(name, format!("🔄 Restarting task '{}': {}", name, fired));
+                    process.restart(ctx.config, task, grace)?;
+                    // whatever the old process printed after this no longer matters
+                    break;
+                }
+                Action::Run(target) => {
+                    // a hook that is still running already covers this match
+                    if hooks.get(target).is_some_and(|hook| !hook.is_finished()) {
+                        continue;
+                    }
+
+                    let config = ctx.config;
+                    let target = target.clone();
+                    let hook = scope.spawn({
+                        let target = target.clone();
+                        move || run_hook(config, &target, &fired)
+                    });
+                    hooks.insert(target, hook);
+                }
+                Action::Notify => notify(name, &fired),
+                Action::Ignore => {}
+            }
+        }
+
+        if process.has_exited() {
+            return Ok(ready);
+        }
+
+        if ready {
+            thread::sleep(SUPERVISE_INTERVAL);
+            continue;
+        }
+
+        if let Some(port) = task.port_check.filter(|_| !port_ready)
+            && Instant::now() >= next_probe
+        {
+            port_ready = port_is_open(port);
+            next_probe = Instant::now() + interval;
+        }
+
+        if !output_ready {
+            output_ready = process.logged_ready();
+        }
+
+        if port_ready && output_ready {
+            match (task.port_check, &task.ready_on) {
+                (Some(port), _) => output::status(
+                    name,
+                    format!("🟢 Task '{}' is ready on port {}", name, port),
+                ),
+                (None, Some(selector)) => output::status(
+                    name,
+                    format!("🟢 Task '{}' is ready, matched '{}'", name, selector),
+                ),
+                (None, None) => {}
+            }
+
+            on_ready();
+            ready = true;
+        } else if Instant::now() >= deadline {
+            process.terminate(grace);
+
+            let reason = match task.port_check.filter(|_| !port_ready) {
+                Some(port) => format!("Port {} did not accept connections", port),
+                None => format!(
+                    "No output matched ready_on '{}'",
+                    task.ready_on.as_deref().unwrap_or_default()
+                ),
+            };
+            return Err(anyhow::anyhow!("{} within {}s", reason, timeout.as_secs()));
+        }
+
+        thread::sleep(SUPERVISE_INTERVAL);
+    }
+}
+
+/// Run the task a `run:<task>` action points to, with what the pattern matched in its
+/// environment. Hooks are not part of the run, so their output is not written to the run log
+pub fn run_hook(config: &Config, target: &str, fired: &FiredAction) {
+    let Some(hook) = config.get_task(target) else {
+        return;
+    };
+
+    output::status(
+        target,
+        format!("🪝 Running task '{}' for: {}", target, fired),
+    );
+
+    let mut hook = hook.clone();
+    hook.env.get_or_insert_default().extend(fired.env());
+
+    let stops = StopRequests::default();
+    let ctx = RunContext {
+        config,
+        run_log: None,
+        cache: None,
+        output: OutputOptions {
+            width: target.len(),
+            ..Default::default()
+        },
+        stops: &stops,
+    };
+    // the outcome has been printed, and a failing hook does not fail the task that fired it
+    let _ = run_task(&ctx, target, &hook, &|| {});
+}
+
+/// Call attention to a line matched by a `notify` pattern
+pub fn notify(name: &str, fired: &FiredAction) {
+    output::status(
+        name,
+        format!(
+            "\x07🔔 Task '{}': {}",
+            name,
+            matcher::level_style("warn").bold().apply_to(fired)
+        ),
+    );
+}
+
+pub fn fail_error(fired: &FiredAction) -> anyhow::Error {
+    anyhow::anyhow!("Output matched a fail pattern: {}", fired)
+}
+
+/// Check whether something on this machine accepts connections on the port
+fn port_is_open(port: u16) -> bool {
+    let Ok(addrs) = ("localhost", port).to_socket_addrs() else {
+        return false;
+    };
+
+    addrs
+        .into_iter()
+        .any(|addr| TcpStream::connect_timeout(&addr, Duration::from_millis(200)).is_ok())
+}
+
+/// Send a signal to every process in the process group a task runs in. Returns false when no
+/// process is left in the group
+fn signal_group(pgid: u32, signal: libc::c_int) -> bool {
+    unsafe { libc::kill(-(pgid as libc::pid_t), signal) == 0 }
+}
+
+/// Send every line the process writes to `tx`, until the pipe is closed. Lines that are not
+/// valid UTF-8 are passed on lossily, so the pipe is drained no matter what the process writes
+fn forward_lines(pipe: impl Read, stream: Stream, tx: &mpsc::Sender<(Stream, String)>) {
+    let mut reader = BufReader::new(pipe);
+    let mut buf = Vec::new();
+
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => return,
+            Ok(_) => {
+                let line = buf.strip_suffix(b"\n").unwrap_or(&buf);
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                let _ = tx.send((stream, String::from_utf8_lossy(line).into_owned()));
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(_) => return,
+        }
+    }
+}
+
+/// A task command that has been started, with its output being matched against the task's
+/// parsers and forwarded to the terminal and its log file
+pub struct RunningProcess {
+    name: String,
+    command: String,
+    output: OutputOptions,
+    child: Child,
+    printer: Option<JoinHandle<(MatchSummary, Option<TaskLog>)>>,
+    summary: MatchSummary,
+    /// Handed back by the printer thread once all output has been read
+    task_log: Option<TaskLog>,
+    actions: mpsc::Receiver<FiredAction>,
+    /// Set by the printer thread once a line matches the task's `ready_on` selector
+    logged_ready: Arc<AtomicBool>,
+    /// Number of times a `restart` pattern restarted the process
+    restarts: u32,
+    /// Set once the process has been waited on, `None` if it was killed by a signal
+    pub exit_code: Option<i32>,
+}
+
+impl RunningProcess {
+    pub fn spawn(
+        config: &Config,
+        name: &str,
+        task: &Task,
+        output: &OutputOptions,
+        mut task_log: Option<TaskLog>,
+    ) -> anyhow::Result<Self> {
+        let matcher = OutputMatcher::for_task(config, task)?;
+        let ready_on = task.ready_on.as_deref().map(MatchSelector::parse);
+        let logged_ready = Arc::new(AtomicBool::new(false));
+
+        let shell = Shell::for_task(config, task);
+        let mut command = shell.command(&task.command)?;
+
+        command.current_dir(config.task_dir(task));
+
+        if let Some(env_vars) = &task.env {
+            for (key, value) in env_vars {
+                command.env(key, value);
+            }
+        }
+
+        command.stdout(Stdio::piped());
+        command.stderr(Stdio::piped());
+        // a process group of its own, so whatever the command starts can be stopped with it
+        command.process_group(0);
+
+        let mut child = command
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("Failed to start command '{}': {}", task.command, e))?;
+        shutdown::register(child.id());
+
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Failed to capture stdout"))?;
+        let stderr = child
+            .stderr
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Failed to capture stderr"))?;
+
+        let (tx, rx) = mpsc::channel();
+
+        let tx_stdout = tx.clone();
+        let stdout_handle =
+            thread::spawn(move || forward_lines(stdout, Stream::Stdout, &tx_stdout));
+
+        let tx_stderr = tx.clone();
+        let stderr_handle =
+            thread::spawn(move || forward_lines(stderr, Stream::Stderr, &tx_stderr));
+
+        drop(tx); // close sending end
+
+        let (actions_tx, actions) = mpsc::channel();
+        let printer_ready = logged_ready.clone();
+        let mut terminal = TaskOutput::new(name, output);
+        let printer = thread::spawn(move || {
+            let mut summary = MatchSummary::default();
+
+            for (stream, line) in rx {
+                if let Some(task_log) = task_log.as_mut() {
+                    match stream {
+                        Stream::Stdout => task_log.write_line("out", &line),
+                        Stream::Stderr => task_log.write_line("err", &line),
+                    }
+                }
+
+                let (line, level) = match matcher.match_line(&line) {
+                    Some(line_match) if line_match.action == Some(&Action::Ignore) => continue,
+                    Some(line_match) => {
+                        summary.record(&line_match);
+
+                        if ready_on.as_ref().is_some_and(|s| s.matches(&line_match)) {
+                            printer_ready.store(true, Ordering::SeqCst);
+                        }
+                        if let Some(action) = line_match.action {
+                            let _ = actions_tx.send(FiredAction::new(action, &line, &line_match));
+                        }
+                        (
+                            matcher::format_match(&line, &line_match),
+                            Some(line_match.level),
+                        )
+                    }
+                    None => (line, None),
+                };
+
+                terminal.line(stream, &line, level);
+            }
+
+            let _ = stdout_handle.join();
+            let _ = stderr_handle.join();
+            terminal.flush();
+
+            (summary, task_log)
+        });
+
+        Ok(Self {
+            name: name.to_string(),
+            command: task.command.clone(),
+            output: *output,
+            child,
+            printer: Some(printer),
+            summary: MatchSummary::default(),
+            task_log: None,
+            actions,
+            logged_ready,
+            restarts: 0,
+            exit_code: None,
+        })
+    }
+
+    /// Whether a line of output matched the task's `ready_on` selector
+    pub fn logged_ready(&self) -> bool {
+        self.logged_ready.load(Ordering::SeqCst)
+    }
+
+    /// Actions of patterns the output matched since the last call
+    pub fn fired_actions(&self) -> Vec<FiredAction> {
+        self.actions.try_iter().collect()
+    }
+
+    /// Stop the process and start the task again, writing to the same log file. Fails, leaving
+    /// the process stopped, once it was restarted `MAX_RESTARTS` times
+    pub fn restart(&mut self, config: &Config, task: &Task, grace: Duration) -> anyhow::Result<()> {
+        self.terminate(grace);
+        let _ = self.wait();
+
+        if self.restarts >= MAX_RESTARTS {
+            return Err(anyhow::anyhow!(
+                "Restarted {} times already, giving up",
+                MAX_RESTARTS
+            ));
+        }
+
+        let name = self.name.clone();
+        let output = self.output;
+        let restarts = self.restarts + 1;
+        *self = Self::spawn(config, &name, task, &output, self.task_log.take())?;
+        self.restarts = restarts;
+        Ok(())
+    }
+
+    /// What the parsers matched, complete once the process has been waited on
+    pub fn summary(&self) -> &MatchSummary {
+        &self.summary
+    }
+
+    /// The log file, handed back once the process has been waited on
+    pub fn take_task_log(&mut self) -> Option<TaskLog> {
+        self.task_log.take()
+    }
+
+    /// Check whether the process has exited, without blocking
+    pub fn has_exited(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(Some(_)))
+    }
+
+    /// Ask the process and everything it started to stop with SIGTERM, and kill whatever is
+    /// still alive after `grace` with SIGKILL
+    pub fn terminate(&mut self, grace: Duration) {
+        let pgid = self.child.id();
+        if !signal_group(pgid, libc::SIGTERM) {
+            return;
+        }
+
+        let deadline = Instant::now() + grace;
+        while Instant::now() < deadline {
+            // reap the process so the group empties once it and its children are gone
+            if self.has_exited() && !signal_group(pgid, 0) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        signal_group(pgid, libc::SIGKILL);
+    }
+
+    /// Wait for the process to exit and all of its output to be printed
+    pub fn wait(&mut self) -> anyhow::Result<()> {
+        let status = self
+            .child
+            .wait()
+            .map_err(|e| anyhow::anyhow!("Failed to wait for process: {}", e))?;
+        shutdown::unregister(self.child.id());
+
+        if let Some(printer) = self.printer.take()
+            && let Ok((summary, task_log)) = printer.join()
+        {
+            self.summary = summary;
+            self.task_log = task_log;
+        }
+        self.exit_code = status.code();
+
+        if !status.success() {
+            return Err(anyhow::anyhow!(
+                "Command '{}' failed with exit code: {}",
+                self.command,
+                status.code().unwrap_or(-1)
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Print the roll-up of everything the task's parsers matched, once the process has exited
+    pub fn print_summary(&self) {
+        if !self.summary.is_empty() {
+            output::status(&self.name, matcher::format_summary(&self.summary));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_backoff() {
+        let config = Config::load_from_string(
+            r#"
+[tasks.install]
+command = "yarn install"
+retries = 3
+retry_delay = 2
+retry_backoff = true
+            "#,
+        )
+        .unwrap();
+        let task = config.get_task("install").unwrap();
+
+        assert_eq!(retry_delay(task, 1), Duration::from_secs(2));
+        assert_eq!(retry_delay(task, 2), Duration::from_secs(4));
+        assert_eq!(retry_delay(task, 3), Duration::from_secs(8));
+    }
+
+    /// Supervise the task until it exits, returning the outcome, how long it took and the
+    /// process group it ran in
+    fn supervise_task(config: &Config, name: &str) -> (anyhow::Result<bool>, Duration, u32) {
+        let task = config.get_task(name).unwrap();
+        let stops = StopRequests::default();
+        let ctx = RunContext {
+            config,
+            run_log: None,
+            cache: None,
+            output: OutputOptions::default(),
+            stops: &stops,
+        };
+
+        let start = Instant::now();
+        let mut process =
+            RunningProcess::spawn(config, name, task, &OutputOptions::default(), None).unwrap();
+        let pgid = process.child.id();
+
+        let result =
+            thread::scope(|scope| supervise(scope, &mut process, &ctx, name, task, &|| {}));
+        let _ = process.wait();
+
+        (result, start.elapsed(), pgid)
+    }
+
+    #[test]
+    fn test_timeout_terminates_process_group() {
+        let config = Config::load_from_string(
+            r#"
+[tasks.slow]
+command = "sleep 30 & sleep 30"
+timeout = 1
+            "#,
+        )
+        .unwrap();
+
+        let (result, elapsed, pgid) = supervise_task(&config, "slow");
+
+        assert!(result.unwrap_err().is::<TimedOut>());
+        assert!(elapsed < Duration::from_secs(5), "took {:?}", elapsed);
+        // the background sleep went down with the shell
+        assert!(!signal_group(pgid, 0));
+    }
+
+    #[test]
+    fn test_task_timeout_and_kill_grace_override_global() {
+        let config = Config::load_from_string(
+            r#"
+[global]
+timeout = 60
+kill_grace = 60
+
+[tasks.stubborn]
+command = "trap '' TERM; exec sleep 30"
+timeout = 1
+kill_grace = 1
+            "#,
+        )
+        .unwrap();
+
+        let (result, elapsed, pgid) = supervise_task(&config, "stubborn");
+
+        // SIGTERM is ignored, so the process only goes away once SIGKILL follows the grace
+        let err = result.unwrap_err();
+        assert!(matches!(err.downcast_ref::<TimedOut>(), Some(TimedOut(1))));
+        assert!(elapsed >= Duration::from_secs(2), "took {:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(6), "took {:?}", elapsed);
+        assert!(!signal_group(pgid, 0));
+    }
+
+    #[test]
+    fn test_restart_action_gives_up_after_max_restarts() {
+        let config = Config::load_from_string(
+            r#"
+[tasks.flaky]
+command = "echo 'port taken'; exec sleep 30"
+parsers = ["flaky"]
+
+[parsers.flaky]
+patterns = [{ regex = "port taken", level = "error", action = "restart" }]
+            "#,
+        )
+        .unwrap();
+
+        let (result, elapsed, _) = supervise_task(&config, "flaky");
+
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("giving up"), "{}", err);
+        assert!(elapsed < Duration::from_secs(10), "took {:?}", elapsed);
+    }
+}
@@ -0,0 +1,639 @@
+use crate::cache::Cache;
+use crate::config::{Config, Task};
+use crate::logs::RunLog;
+use crate::output::OutputOptions;
+use crate::runner::{RunContext, StopRequests, Stopped};
+use crate::shutdown::Interrupted;
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet, VecDeque},
+    panic::{self, AssertUnwindSafe},
+    sync::mpsc,
+    thread,
+};
+
+/// Runs and stops the processes of tasks on behalf of the scheduler
+pub trait TaskRunner: Sync {
+    /// Run a task to completion, calling `on_ready` if it passes a readiness check while it
+    /// keeps running
+    fn run(&self, name: &str, task: &Task, on_ready: &dyn Fn()) -> anyhow::Result<()>;
+
+    /// Ask a running task to stop
+    fn stop(&self, name: &str);
+
+    /// Whether taskr is shutting down, in which case no more tasks are started
+    fn interrupted(&self) -> bool {
+        false
+    }
+}
+
+enum Event<'t> {
+    Ready(&'t str),
+    Finished(&'t str, anyhow::Result<()>),
+}
+
+/// Runs a set of tasks concurrently, starting every task as soon as all of its
+/// dependencies have succeeded, with at most `max_parallel` tasks running at once
+pub struct Scheduler<'a> {
+    config: &'a Config,
+    max_parallel: usize,
+    run_log: Option<&'a RunLog>,
+    cache: Option<&'a Cache>,
+    output: OutputOptions,
+    /// Keep starting tasks that do not depend on a failed task, instead of stopping at the
+    /// first failure
+    keep_going: bool,
+}
+
+impl<'a> Scheduler<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        let max_parallel = config.get_global_settings().max_parallel.max(1) as usize;
+
+        Self {
+            config,
+            max_parallel,
+            run_log: None,
+            cache: None,
+            output: OutputOptions::default(),
+            keep_going: false,
+        }
+    }
+
+    /// Skip tasks that are up to date according to the cache
+    pub fn with_cache(mut self, cache: &'a Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn with_keep_going(mut self, keep_going: bool) -> Self {
+        self.keep_going = keep_going;
+        self
+    }
+
+    /// How the output of the tasks is printed, the width of the task names is set by `run`
+    pub fn with_output(mut self, output: OutputOptions) -> Self {
+        self.output = output;
+        self
+    }
+
+    /// Write the output of every task, and a manifest of the run, to the given run log
+    pub fn with_run_log(mut self, run_log: &'a RunLog) -> Self {
+        self.run_log = Some(run_log);
+        self
+    }
+
+    /// Run the given tasks, which should be in execution order as returned by
+    /// `Config::get_exec_order`. Fails with every task that failed
+    pub fn run(&self, tasks: &[String]) -> anyhow::Result<()> {
+        let stops = StopRequests::default();
+        self.run_with(tasks, &self.context(tasks, &stops))
+    }
+
+    /// What the given tasks run with, for runners that wrap the default one
+    pub fn context<'s>(&'s self, tasks: &[String], stops: &'s StopRequests) -> RunContext<'s> {
+        RunContext {
+            config: self.config,
+            run_log: self.run_log,
+            cache: self.cache,
+            output: OutputOptions {
+                width: tasks.iter().map(String::len).max().unwrap_or_default(),
+                ..self.output
+            },
+            stops,
+        }
+    }
+
+    /// Like `run`, but with the task processes run and stopped by `runner`
+    pub fn run_with(&self, tasks: &[String], runner: &impl TaskRunner) -> anyhow::Result<()> {
+        let scheduled: HashSet<&str> = tasks.iter().map(String::as_str).collect();
+
+        // number of unfinished dependencies per task, only counting the tasks in this run
+        let mut pending: HashMap<&str, usize> = HashMap::new();
+        let mut ready = VecDeque::new();
+
+        for name in tasks {
+            let task = self.get_task(name)?;
+            let deps = task.depends_on.as_ref().map_or(0, |deps| {
+                deps.iter()
+                    .filter(|d| scheduled.contains(d.as_str()))
+                    .count()
+            });
+
+            if deps == 0 {
+                ready.push_back(name.as_str());
+            } else {
+                pending.insert(name.as_str(), deps);
+            }
+        }
+
+        let mut failed = Vec::new();
+        let mut completed = Vec::new();
+
+        thread::scope(|scope| -> anyhow::Result<()> {
+            let (tx, rx) = mpsc::channel();
+            // tasks taking up a slot, services that became ready no longer do
+            let mut running = 0;
+            // tasks that passed their readiness check and keep running in the background
+            let mut services = HashSet::new();
+            let mut stopped = HashSet::new();
+
+            loop {
+                while running < self.max_parallel
+                    && (failed.is_empty() || self.keep_going)
+                    && !runner.interrupted()
+                {
+                    let Some(name) = ready.pop_front() else {
+                        break;
+                    };
+
+                    let task = self.get_task(name)?;
+                    let tx = tx.clone();
+
+                    scope.spawn(move || {
+                        let ready_tx = tx.clone();
+                        let on_ready = move || {
+                            let _ = ready_tx.send(Event::Ready(name));
+                        };
+
+                        // a panicking task still has to finish, or the run waits for it forever
+                        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                            runner.run(name, task, &on_ready)
+                        }))
+                        .unwrap_or_else(|panic| Err(panicked(panic)));
+                        let _ = tx.send(Event::Finished(name, result));
+                    });
+                    running += 1;
+                }
+
+                if running == 0 {
+                    if services.is_empty() {
+                        break;
+                    }
+
+                    // nothing else is going to run, so stop the services that were only
+                    // started for their dependents. Services that were asked for themselves
+                    // keep running until they exit
+                    for &service in &services {
+                        if (!failed.is_empty() || self.has_dependents(service, &scheduled))
+                            && stopped.insert(service)
+                        {
+                            runner.stop(service);
+                        }
+                    }
+                }
+
+                match rx.recv()? {
+                    Event::Ready(name) => {
+                        running -= 1;
+                        services.insert(name);
+                        self.release(name, &mut pending, &mut ready);
+                    }
+                    Event::Finished(name, result) => {
+                        // services gave up their slot and released their dependents already
+                        if !services.remove(name) {
+                            running -= 1;
+                            if result.is_ok() {
+                                self.release(name, &mut pending, &mut ready);
+                            }
+                        }
+
+                        match result {
+                            Ok(()) => completed.push(name),
+                            Err(e) => failed.push((name, e)),
+                        }
+                    }
+                }
+            }
+
+            Ok(())
+        })?;
+
+        let mut stuck: Vec<&str> = pending.into_keys().collect();
+        stuck.sort();
+
+        if runner.interrupted() {
+            let mut not_started: Vec<&str> = ready.into_iter().chain(stuck).collect();
+            not_started.sort();
+            return Err(shutdown_summary(&completed, &failed, &not_started));
+        }
+
+        match failed.as_slice() {
+            [] if stuck.is_empty() => Ok(()),
+            [] => Err(anyhow::anyhow!(
+                "Tasks could not be scheduled: {}",
+                stuck.join(", ")
+            )),
+            [(name, err)] if !self.keep_going || stuck.is_empty() => {
+                Err(anyhow::anyhow!("Task '{}' failed: {}", name, err))
+            }
+            _ => {
+                let mut message = format!("{} of {} tasks failed:", failed.len(), tasks.len());
+                for (name, err) in &failed {
+                    message.push_str(&format!("\n  ❌ {}: {}", name, err));
+                }
+                if self.keep_going && !stuck.is_empty() {
+                    message.push_str(&format!(
+                        "\n  ⏭️  Skipped after a dependency failed: {}",
+                        stuck.join(", ")
+                    ));
+                }
+
+                Err(anyhow::anyhow!(message))
+            }
+        }
+    }
+
+    /// Mark a task as done for its dependents, queueing the ones that have no unfinished
+    /// dependencies left
+    fn release<'t>(
+        &self,
+        name: &str,
+        pending: &mut HashMap<&'t str, usize>,
+        ready: &mut VecDeque<&'t str>,
+    ) {
+        for dependent in self.config.get_dependent_tasks(name) {
+            if let Some(count) = pending.get_mut(dependent.as_str()) {
+                *count -= 1;
+                if *count == 0 {
+                    let (name, _) = pending.remove_entry(dependent.as_str()).unwrap();
+                    ready.push_back(name);
+                }
+            }
+        }
+    }
+
+    fn has_dependents(&self, name: &str, scheduled: &HashSet<&str>) -> bool {
+        self.config
+            .get_dependent_tasks(name)
+            .iter()
+            .any(|dependent| scheduled.contains(dependent.as_str()))
+    }
+
+    fn get_task(&self, name: &str) -> anyhow::Result<&'a Task> {
+        self.config
+            .get_task(name)
+            .ok_or_else(|| anyhow::anyhow!("Task '{}' not found in project configuration", name))
+    }
+}
+
+/// The failure of a task whose thread panicked, with the panic message
+fn panicked(panic: Box<dyn Any + Send>) -> anyhow::Error {
+    let message = panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+
+    anyhow::anyhow!("Task panicked: {}", message)
+}
+
+/// Report what happened to every task when a run was interrupted
+fn shutdown_summary(
+    completed: &[&str],
+    failed: &[(&str, anyhow::Error)],
+    not_started: &[&str],
+) -> anyhow::Error {
+    let names = |interrupted: bool| -> Vec<&str> {
+        failed
+            .iter()
+            .filter(|(_, e)| (e.is::<Interrupted>() || e.is::<Stopped>()) == interrupted)
+            .map(|(name, _)| *name)
+            .collect()
+    };
+
+    let mut message = String::from("Interrupted, shutdown summary:");
+    for (label, tasks) in [
+        ("✅ completed", completed.to_vec()),
+        ("⏹️  stopped", names(true)),
+        ("❌ failed", names(false)),
+        ("⏭️  not started", not_started.to_vec()),
+    ] {
+        if !tasks.is_empty() {
+            message.push_str(&format!("\n  {}: {}", label, tasks.join(", ")));
+        }
+    }
+
+    anyhow::anyhow!(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{
+            Mutex,
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    /// Runs tasks with a closure instead of spawning processes
+    struct FakeRunner<F> {
+        run: F,
+        stopped: Mutex<Vec<String>>,
+        interrupted: AtomicBool,
+    }
+
+    impl<F> FakeRunner<F>
+    where
+        F: Fn(&str, &dyn Fn()) -> anyhow::Result<()> + Sync,
+    {
+        fn new(run: F) -> Self {
+            Self {
+                run,
+                stopped: Mutex::new(Vec::new()),
+                interrupted: AtomicBool::new(false),
+            }
+        }
+    }
+
+    impl<F> TaskRunner for FakeRunner<F>
+    where
+        F: Fn(&str, &dyn Fn()) -> anyhow::Result<()> + Sync,
+    {
+        fn run(&self, name: &str, _: &Task, on_ready: &dyn Fn()) -> anyhow::Result<()> {
+            (self.run)(name, on_ready)
+        }
+
+        fn stop(&self, name: &str) {
+            self.stopped.lock().unwrap().push(name.to_string());
+        }
+
+        fn interrupted(&self) -> bool {
+            self.interrupted.load(Ordering::SeqCst)
+        }
+    }
+
+    const CONFIG: &str = r#"
+[global]
+max_parallel = 2
+
+[tasks.install]
+command = "yarn install"
+
+[tasks.codegen]
+command = "yarn codegen"
+
+[tasks.build]
+command = "yarn build"
+depends_on = ["install", "codegen"]
+
+[tasks.lint]
+command = "yarn lint"
+depends_on = ["install"]
+
+[tasks.test]
+command = "yarn test"
+depends_on = ["build"]
+        "#;
+
+    #[test]
+    fn test_runs_dependencies_first() {
+        let config = Config::load_from_string(CONFIG).unwrap();
+        let finished = Mutex::new(Vec::new());
+
+        let order = config.get_exec_order("test").unwrap();
+        Scheduler::new(&config)
+            .run_with(
+                &order,
+                &FakeRunner::new(|name, _| {
+                    finished.lock().unwrap().push(name.to_string());
+                    Ok(())
+                }),
+            )
+            .unwrap();
+
+        let finished = finished.into_inner().unwrap();
+        let position = |name: &str| finished.iter().position(|t| t == name).unwrap();
+
+        assert_eq!(finished.len(), 4);
+        assert!(position("install") < position("build"));
+        assert!(position("codegen") < position("build"));
+        assert!(position("build") < position("test"));
+    }
+
+    #[test]
+    fn test_respects_max_parallel() {
+        let config = Config::load_from_string(CONFIG).unwrap();
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        let tasks: Vec<String> = ["install", "codegen", "lint", "build", "test"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+
+        Scheduler::new(&config)
+            .run_with(
+                &tasks,
+                &FakeRunner::new(|_, _| {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok(())
+                }),
+            )
+            .unwrap();
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_failure_stops_dependents() {
+        let config = Config::load_from_string(CONFIG).unwrap();
+        let started = Mutex::new(Vec::new());
+
+        let order = config.get_exec_order("test").unwrap();
+        let result = Scheduler::new(&config).run_with(
+            &order,
+            &FakeRunner::new(|name, _| {
+                started.lock().unwrap().push(name.to_string());
+                if name == "install" {
+                    return Err(anyhow::anyhow!("boom"));
+                }
+                Ok(())
+            }),
+        );
+
+        assert!(result.is_err());
+        let started = started.into_inner().unwrap();
+        assert!(!started.contains(&"build".to_string()));
+        assert!(!started.contains(&"test".to_string()));
+    }
+
+    #[test]
+    fn test_panicking_task_fails_instead_of_hanging() {
+        let config = Config::load_from_string(CONFIG).unwrap();
+
+        let order = config.get_exec_order("build").unwrap();
+        let result = Scheduler::new(&config).run_with(
+            &order,
+            &FakeRunner::new(|name, _| {
+                if name == "codegen" {
+                    panic!("broken pipe");
+                }
+                Ok(())
+            }),
+        );
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Task 'codegen' failed: Task panicked: broken pipe"
+        );
+    }
+
+    #[test]
+    fn test_keep_going_runs_independent_branches() {
+        let config = Config::load_from_string(CONFIG).unwrap();
+        let started = Mutex::new(Vec::new());
+
+        let tasks: Vec<String> = ["install", "codegen", "lint", "build", "test"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        let result = Scheduler::new(&config).with_keep_going(true).run_with(
+            &tasks,
+            &FakeRunner::new(|name, _| {
+                started.lock().unwrap().push(name.to_string());
+                if name == "codegen" {
+                    return Err(anyhow::anyhow!("boom"));
+                }
+                Ok(())
+            }),
+        );
+
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("codegen: boom"));
+        assert!(message.contains("Skipped after a dependency failed: build, test"));
+
+        let started = started.into_inner().unwrap();
+        assert!(started.contains(&"lint".to_string()));
+        assert!(!started.contains(&"build".to_string()));
+    }
+
+    #[test]
+    fn test_interrupt_reports_shutdown_summary() {
+        let config = Config::load_from_string(CONFIG).unwrap();
+        let signalled = AtomicBool::new(false);
+        let shutting_down = AtomicBool::new(false);
+
+        let tasks: Vec<String> = ["install", "codegen", "build", "test"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        let runner = FakeRunner::new(|name, _| {
+            if name == "codegen" {
+                // Ctrl-C while codegen runs, which stops once taskr is shutting down
+                signalled.store(true, Ordering::SeqCst);
+                while !shutting_down.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                return Err(Interrupted.into());
+            }
+            Ok(())
+        });
+
+        let result = thread::scope(|scope| {
+            scope.spawn(|| {
+                while !signalled.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                runner.interrupted.store(true, Ordering::SeqCst);
+                shutting_down.store(true, Ordering::SeqCst);
+            });
+
+            Scheduler::new(&config).run_with(&tasks, &runner)
+        });
+
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("stopped: codegen"));
+        assert!(message.contains("not started: build, test"));
+    }
+
+    #[test]
+    fn test_ready_service_releases_dependents() {
+        let config = Config::load_from_string(
+            r#"
+[global]
+max_parallel = 1
+
+[tasks.dev]
+command = "yarn dev"
+port_check = 3000
+
+[tasks.e2e]
+command = "yarn e2e"
+depends_on = ["dev"]
+            "#,
+        )
+        .unwrap();
+
+        let e2e_ran = AtomicBool::new(false);
+        let dev_stopped = AtomicBool::new(false);
+
+        let runner = FakeRunner::new(|name, on_ready| {
+            if name == "dev" {
+                on_ready();
+                // keep serving until the scheduler stops us
+                while !dev_stopped.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(5));
+                }
+            } else {
+                e2e_ran.store(true, Ordering::SeqCst);
+            }
+            Ok(())
+        });
+
+        let order = config.get_exec_order("e2e").unwrap();
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                while runner.stopped.lock().unwrap().is_empty() {
+                    thread::sleep(Duration::from_millis(5));
+                }
+                dev_stopped.store(true, Ordering::SeqCst);
+            });
+
+            Scheduler::new(&config).run_with(&order, &runner).unwrap();
+        });
+
+        assert!(e2e_ran.load(Ordering::SeqCst));
+        assert_eq!(*runner.stopped.lock().unwrap(), vec!["dev"]);
+    }
+
+    #[test]
+    fn test_stopping_unready_task_holds_back_dependents() {
+        let built = crate::logs::test_util::temp_dir("stop-unready").join("built");
+        let config = Config::load_from_string(&format!(
+            r#"
+[tasks.install]
+command = "exec sleep 30"
+
+[tasks.build]
+command = "touch '{}'"
+depends_on = ["install"]
+            "#,
+            built.display()
+        ))
+        .unwrap();
+
+        let order = config.get_exec_order("build").unwrap();
+        let scheduler = Scheduler::new(&config);
+        let stops = StopRequests::default();
+        let ctx = scheduler.context(&order, &stops);
+
+        let result = thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(Duration::from_millis(200));
+                ctx.stop("install");
+            });
+
+            scheduler.run_with(&order, &ctx)
+        });
+
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("Task 'install' failed"), "{}", err);
+        assert!(err.contains("Stopped before becoming ready"), "{}", err);
+        assert!(!built.exists());
+    }
+}
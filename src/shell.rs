@@ -0,0 +1,198 @@
+use crate::config::{Config, Task};
+use std::process::Command;
+
+/// How the command string of a task is turned into a process
+#[derive(Debug, PartialEq)]
+pub enum Shell {
+    /// Run through a shell program as `<program> [args] -c <command>`, so quoting, pipes, `&&`,
+    /// redirects, globs and `$VAR` expansion all work. The value can include arguments for the
+    /// shell, like `bash -c` or `zsh -lc`
+    Program(String),
+    /// Split the command into words POSIX-style and execute it without a shell
+    Direct,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum SplitError {
+    #[error("Unterminated {0} quote")]
+    UnterminatedQuote(&'static str),
+
+    #[error("Trailing backslash")]
+    TrailingBackslash,
+}
+
+impl Shell {
+    pub fn parse(value: &str) -> Self {
+        match value.trim() {
+            "none" | "direct" => Shell::Direct,
+            program => Shell::Program(program.to_string()),
+        }
+    }
+
+    /// The shell of a task, falling back to the global shell and then to `sh`
+    pub fn for_task(config: &Config, task: &Task) -> Self {
+        Self::parse(&config.get_task_settings(task).shell)
+    }
+
+    /// Build the process for a command line
+    pub fn command(&self, command_line: &str) -> anyhow::Result<Command> {
+        match self {
+            Shell::Program(shell) => {
+                let words = split_words(shell)
+                    .map_err(|e| anyhow::anyhow!("Invalid shell '{}': {}", shell, e))?;
+
+                let (program, args) = words
+                    .split_first()
+                    .ok_or_else(|| anyhow::anyhow!("Empty shell"))?;
+
+                let mut command = Command::new(program);
+                command.args(args);
+                // `-c`, or a group of flags ending in it like `-lc`, has to come last
+                if !args.last().is_some_and(|arg| {
+                    arg.starts_with('-') && !arg.starts_with("--") && arg.ends_with('c')
+                }) {
+                    command.arg("-c");
+                }
+                command.arg(command_line);
+                Ok(command)
+            }
+            Shell::Direct => {
+                let words = split_words(command_line)
+                    .map_err(|e| anyhow::anyhow!("Invalid command '{}': {}", command_line, e))?;
+
+                let (program, args) = words
+                    .split_first()
+                    .ok_or_else(|| anyhow::anyhow!("Empty command"))?;
+
+                let mut command = Command::new(program);
+                command.args(args);
+                Ok(command)
+            }
+        }
+    }
+}
+
+/// Split a command line into words the way a POSIX shell does, without any expansion.
+/// Single quotes keep everything literal, double quotes only allow `\` to escape `$`, `` ` ``,
+/// `"`, `\` and newlines, and outside of quotes `\` escapes any character
+pub fn split_words(input: &str) -> Result<Vec<String>, SplitError> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    // a word can be empty but still exist, e.g. `''`
+    let mut in_word = false;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(SplitError::UnterminatedQuote("single")),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('\n') => {}
+                            Some(c @ ('$' | '`' | '"' | '\\')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(SplitError::UnterminatedQuote("double")),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(SplitError::UnterminatedQuote("double")),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some('\n') => {}
+                Some(c) => {
+                    in_word = true;
+                    word.push(c);
+                }
+                None => return Err(SplitError::TrailingBackslash),
+            },
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+
+    if in_word {
+        words.push(word);
+    }
+
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_words_quotes() {
+        assert_eq!(
+            split_words("echo 'hello world'").unwrap(),
+            vec!["echo", "hello world"]
+        );
+        assert_eq!(
+            split_words(r#"node -e "console.log(\"hi\")" ''"#).unwrap(),
+            vec!["node", "-e", r#"console.log("hi")"#, ""]
+        );
+        assert_eq!(
+            split_words(r#"grep foo\ bar "a\nb""#).unwrap(),
+            vec!["grep", "foo bar", r"a\nb"]
+        );
+    }
+
+    #[test]
+    fn test_split_words_errors() {
+        assert_eq!(
+            split_words("echo 'oops"),
+            Err(SplitError::UnterminatedQuote("single"))
+        );
+        assert_eq!(
+            split_words("echo \"oops"),
+            Err(SplitError::UnterminatedQuote("double"))
+        );
+        assert_eq!(split_words("echo \\"), Err(SplitError::TrailingBackslash));
+    }
+
+    #[test]
+    fn test_parse_shell() {
+        assert_eq!(Shell::parse("none"), Shell::Direct);
+        assert_eq!(Shell::parse("direct"), Shell::Direct);
+        assert_eq!(Shell::parse("bash"), Shell::Program("bash".to_string()));
+    }
+
+    #[test]
+    fn test_shell_command_adds_c_once() {
+        let args = |shell: &str| {
+            let command = Shell::parse(shell).command("echo hi").unwrap();
+            let mut words = vec![command.get_program().to_string_lossy().to_string()];
+            words.extend(command.get_args().map(|a| a.to_string_lossy().to_string()));
+            words
+        };
+
+        assert_eq!(args("bash"), vec!["bash", "-c", "echo hi"]);
+        assert_eq!(args("bash -c"), vec!["bash", "-c", "echo hi"]);
+        assert_eq!(args("zsh -lc"), vec!["zsh", "-lc", "echo hi"]);
+        assert_eq!(args("bash -l"), vec!["bash", "-l", "-c", "echo hi"]);
+    }
+}
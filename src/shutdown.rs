@@ -0,0 +1,73 @@
+use std::{
+    collections::BTreeSet,
+    process,
+    sync::{
+        Mutex, Once,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+/// Number of SIGINT/SIGTERM/SIGHUP signals received so far
+static SIGNALS: AtomicUsize = AtomicUsize::new(0);
+
+/// Process groups of the task processes that are currently running
+static GROUPS: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+static INSTALL: Once = Once::new();
+
+/// A task that was stopped because taskr is shutting down
+#[derive(Debug, thiserror::Error)]
+#[error("Interrupted")]
+pub struct Interrupted;
+
+/// Handle Ctrl-C and termination signals: the first one asks every running task to shut down,
+/// which the supervising threads pick up and forward to the task's process group, and the
+/// second one kills every task process right away and exits
+pub fn install() -> anyhow::Result<()> {
+    let mut result = Ok(());
+
+    INSTALL.call_once(|| {
+        result = ctrlc::set_handler(|| {
+            if request() {
+                eprintln!("\n🛑 Shutting down, press Ctrl-C again to kill all tasks");
+                return;
+            }
+
+            eprintln!("\n💀 Killing all tasks");
+            kill_all();
+        })
+        .map_err(|e| anyhow::anyhow!("Failed to install signal handler: {}", e));
+    });
+
+    result
+}
+
+/// Ask every running task to shut down, like the first Ctrl-C does. Returns false when a
+/// shutdown was requested before
+pub fn request() -> bool {
+    SIGNALS.fetch_add(1, Ordering::SeqCst) == 0
+}
+
+/// Whether a signal asked taskr to shut down
+pub fn requested() -> bool {
+    SIGNALS.load(Ordering::SeqCst) > 0
+}
+
+/// Kill every task process right away and exit, like the second Ctrl-C does
+pub fn kill_all() -> ! {
+    for pgid in GROUPS.lock().unwrap().iter() {
+        unsafe {
+            libc::kill(-(*pgid as libc::pid_t), libc::SIGKILL);
+        }
+    }
+    process::exit(130);
+}
+
+/// Remember the process group of a task process, so it can be killed on a forced shutdown
+pub fn register(pgid: u32) {
+    GROUPS.lock().unwrap().insert(pgid);
+}
+
+pub fn unregister(pgid: u32) {
+    GROUPS.lock().unwrap().remove(&pgid);
+}
@@ -1,13 +1,327 @@
-pub struct Watcher {
-    pub current: &'static str,
+use crate::config::{Config, Task};
+use crate::matcher::Action;
+use crate::output::OutputOptions;
+use crate::runner::{self, RunningProcess};
+use crate::scheduler::Scheduler;
+use crate::shutdown;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long a long-running process gets to shut down before it is killed on restart
+const RESTART_GRACE: Duration = Duration::from_secs(5);
+
+/// How often to check whether the current run has exited while waiting for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Longest a burst of changes is waited out before reloading, so files that never stop
+/// changing can't hold the task back forever
+const MAX_DRAIN: Duration = Duration::from_secs(2);
+
+/// Watches the `watch_files` of a task and reruns or restarts it when they change
+pub struct Watcher<'a> {
+    config: &'a Config,
+    name: &'a str,
+    task: &'a Task,
+    base_dir: PathBuf,
+    globs: GlobSet,
+    debounce: Duration,
+    current: Option<RunningProcess>,
+}
+
+impl<'a> Watcher<'a> {
+    pub fn new(config: &'a Config, name: &'a str, debounce: Duration) -> anyhow::Result<Self> {
+        let task = config
+            .get_task(name)
+            .ok_or_else(|| anyhow::anyhow!("Task '{}' not found in project configuration", name))?;
+
+        let patterns = task
+            .watch_files
+            .as_ref()
+            .filter(|patterns| !patterns.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Task '{}' has no watch_files configured", name))?;
+
+        // globs are relative to the directory the task runs in
+        let base_dir = config.task_dir(task);
+        let base_dir = base_dir.canonicalize().map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to resolve directory '{}': {}",
+                base_dir.display(),
+                e
+            )
+        })?;
+
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(
+                Glob::new(pattern)
+                    .map_err(|e| anyhow::anyhow!("Invalid watch pattern '{}': {}", pattern, e))?,
+            );
+        }
+
+        Ok(Self {
+            config,
+            name,
+            task,
+            base_dir,
+            globs: builder.build()?,
+            debounce,
+            current: None,
+        })
+    }
+
+    /// Start the task and block forever, rerunning it whenever a watched file changes
+    pub fn watch(&mut self) -> anyhow::Result<()> {
+        let (tx, rx) = mpsc::channel();
+        let mut fs_watcher = notify::recommended_watcher(tx)?;
+        self.register(&mut fs_watcher)?;
+
+        println!(
+            "👀 Watching {} for task '{}'",
+            self.task.watch_files.as_ref().unwrap().join(", "),
+            self.name
+        );
+
+        self.reload()?;
+
+        loop {
+            // checked on every event too, as a stream of irrelevant ones never times out
+            if shutdown::requested() {
+                self.shut_down();
+                return Ok(());
+            }
+
+            self.handle_actions()?;
+            self.report_exit();
+
+            match rx.recv_timeout(POLL_INTERVAL) {
+                Ok(event) => {
+                    if !self.is_relevant(event) {
+                        continue;
+                    }
+
+                    self.drain(&rx);
+                    if !shutdown::requested() {
+                        println!("🔄 Change detected, reloading task '{}'", self.name);
+                        self.reload()?;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(anyhow::anyhow!("File watcher stopped unexpectedly"));
+                }
+            }
+        }
+    }
+
+    /// Rerun the task, or kill and restart it when `auto_restart` is enabled. Doesn't start it
+    /// again when taskr shuts down while waiting for the current run
+    fn reload(&mut self) -> anyhow::Result<()> {
+        if let Some(mut process) = self.current.take() {
+            if self.task.auto_restart == Some(true) {
+                process.terminate(RESTART_GRACE);
+                let _ = process.wait();
+                println!("⏹️  Stopped task '{}'", self.name);
+            } else {
+                println!(
+                    "   ⏳ Waiting for the current run of '{}' to finish",
+                    self.name
+                );
+                while !process.has_exited() {
+                    if shutdown::requested() {
+                        self.current = Some(process);
+                        self.shut_down();
+                        return Ok(());
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                }
+                self.finish(process);
+            }
+        }
+
+        println!("🚀 Running task '{}'", self.name);
+        println!("   💻 {}", self.task.command);
+        println!("   ─────────────────────────────────");
+
+        match RunningProcess::spawn(
+            self.config,
+            self.name,
+            self.task,
+            &OutputOptions {
+                width: self.name.len(),
+                ..Default::default()
+            },
+            None,
+        ) {
+            Ok(process) => self.current = Some(process),
+            Err(e) => eprintln!("❌ Task '{}' failed: {}", self.name, e),
+        }
+
+        Ok(())
+    }
+
+    /// Watch the directories that can contain files matching the configured globs
+    fn register(&self, fs_watcher: &mut RecommendedWatcher) -> anyhow::Result<()> {
+        for pattern in self.task.watch_files.as_ref().unwrap() {
+            let (dir, mode) = watch_root(&self.base_dir, pattern);
+            if !dir.exists() {
+                eprintln!(
+                    "⚠️  Not watching '{}': {} does not exist",
+                    pattern,
+                    dir.display()
+                );
+                continue;
+            }
+
+            fs_watcher
+                .watch(&dir, mode)
+                .map_err(|e| anyhow::anyhow!("Failed to watch '{}': {}", dir.display(), e))?;
+        }
+
+        Ok(())
+    }
+
+    fn is_relevant(&self, event: notify::Result<Event>) -> bool {
+        let Ok(event) = event else {
+            return false;
+        };
+
+        if matches!(event.kind, EventKind::Access(_)) {
+            return false;
+        }
+
+        event.paths.iter().any(|path| {
+            path.strip_prefix(&self.base_dir)
+                .is_ok_and(|relative| self.globs.is_match(relative))
+        })
+    }
+
+    /// Swallow the rest of a burst of changes, until nothing changed for the debounce period,
+    /// taskr is shutting down, or `MAX_DRAIN` has passed
+    fn drain(&self, rx: &Receiver<notify::Result<Event>>) {
+        let deadline = Instant::now() + self.debounce.max(MAX_DRAIN);
+
+        while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+            if shutdown::requested() || rx.recv_timeout(self.debounce.min(left)).is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Carry out the actions of patterns the current run's output matched
+    fn handle_actions(&mut self) -> anyhow::Result<()> {
+        let Some(process) = self.current.as_mut() else {
+            return Ok(());
+        };
+
+        for fired in process.fired_actions() {
+            match &fired.action {
+                Action::Fail => {
+                    eprintln!("❌ {}", runner::fail_error(&fired));
+                    process.terminate(RESTART_GRACE);
+                    break;
+                }
+                Action::Restart => {
+                    println!("🔄 Restarting task '{}': {}", self.name, fired);
+                    // the stopped run is reported once it's noticed to have exited
+                    if let Err(e) = process.restart(self.config, self.task, RESTART_GRACE) {
+                        eprintln!("❌ Task '{}' failed: {}", self.name, e);
+                    }
+                    break;
+                }
+                Action::Run(target) => runner::run_hook(self.config, target, &fired),
+                Action::Notify => runner::notify(self.name, &fired),
+                Action::Ignore => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stop the current run because taskr is shutting down
+    fn shut_down(&mut self) {
+        if let Some(mut process) = self.current.take() {
+            process.terminate(RESTART_GRACE);
+            let _ = process.wait();
+            println!("⏹️  Stopped task '{}'", self.name);
+        }
+    }
+
+    fn report_exit(&mut self) {
+        if self.current.as_mut().is_some_and(|p| p.has_exited()) {
+            let process = self.current.take().unwrap();
+            self.finish(process);
+            println!("👀 Waiting for changes...");
+        }
+    }
+
+    fn finish(&self, mut process: RunningProcess) {
+        let result = process.wait();
+        process.print_summary();
+
+        match result {
+            Ok(()) => println!("✅ Task '{}' completed successfully", self.name),
+            Err(e) => eprintln!("❌ Task '{}' failed: {}", self.name, e),
+        }
+    }
+}
+
+/// Run the dependencies of a task once, then watch the task itself
+pub fn watch_task(config: &Config, name: &str, debounce: Duration) -> anyhow::Result<()> {
+    let mut watcher = Watcher::new(config, name, debounce)?;
+    shutdown::install()?;
+
+    let deps: Vec<String> = config
+        .get_exec_order(name)?
+        .into_iter()
+        .filter(|task| task != name)
+        .collect();
+
+    Scheduler::new(config).run(&deps)?;
+
+    watcher.watch()
 }
 
-impl Watcher {
-    pub fn watch() {
-        todo!()
+/// Find the directory to watch for a glob: the longest leading path without wildcards.
+/// Anything below that directory could match when the glob has wildcards in its directories
+pub fn watch_root(base_dir: &Path, pattern: &str) -> (PathBuf, RecursiveMode) {
+    let parts: Vec<&str> = pattern.split('/').collect();
+
+    let mut dir = base_dir.to_path_buf();
+    for part in &parts[..parts.len() - 1] {
+        if part.contains(['*', '?', '[', '{']) {
+            return (dir, RecursiveMode::Recursive);
+        }
+        dir.push(part);
+    }
+
+    (dir, RecursiveMode::NonRecursive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watch_root_for_plain_file() {
+        let (dir, mode) = watch_root(Path::new("/repo"), "package.json");
+        assert_eq!(dir, PathBuf::from("/repo"));
+        assert_eq!(mode, RecursiveMode::NonRecursive);
+
+        let (dir, mode) = watch_root(Path::new("/repo"), "src/*.ts");
+        assert_eq!(dir, PathBuf::from("/repo/src"));
+        assert_eq!(mode, RecursiveMode::NonRecursive);
     }
 
-    fn reload(&self) {
-        todo!()
+    #[test]
+    fn test_watch_root_for_wildcard_dirs() {
+        let (dir, mode) = watch_root(Path::new("/repo"), "apps/**/*.tsx");
+        assert_eq!(dir, PathBuf::from("/repo/apps"));
+        assert_eq!(mode, RecursiveMode::Recursive);
     }
 }